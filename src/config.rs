@@ -14,32 +14,80 @@
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
 use lazy_static::lazy_static;
-use serde::Deserialize;
+use log::LevelFilter;
+use serde::{
+    de::{Deserializer, Error},
+    Deserialize,
+};
 use shellexpand;
 use toml;
 
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    env,
+    fs::File,
+    io::Read,
+    path::Path,
+    process::{self, Command, Stdio},
+};
+
+use crate::{
+    keymap::{self, Action, KeyMap},
+    utils::{color::Theme, template::Template},
+};
 
 lazy_static! {
     pub static ref CONFIG: Config = {
-        let path = shellexpand::full("$XDG_CONFIG_HOME/axon.toml")
-            .unwrap_or_else(|_| shellexpand::tilde("~/.config/axon.toml"));
-        let path = Path::new(&*path);
-        if Path::exists(&path) {
-            let mut toml = String::new();
-            let mut file = File::open(path).unwrap();
-            file.read_to_string(&mut toml).unwrap();
-
-            let cfg = toml::from_str::<Config>(&*toml).unwrap();
-            if cfg.autoconnect && !cfg.server.is_some() {
-                panic!("Set to autoconnect but no server specified");
-            }
-
-            cfg
-        } else {
-            Config::default()
+        // Tests must not depend on the user's config
+        if cfg!(test) {
+            return Config::default();
         }
+        // This is first accessed in main, before the TUI is set up
+        load().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        })
+    };
+}
+
+// Reads the config file, if there is one, and applies the overrides of the environment
+fn load() -> Result<Config, String> {
+    let path = shellexpand::full("$XDG_CONFIG_HOME/axon.toml")
+        .unwrap_or_else(|_| shellexpand::tilde("~/.config/axon.toml"));
+    let path = Path::new(&*path);
+    let mut cfg = if Path::exists(&path) {
+        let mut toml = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut toml))
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        toml::from_str::<Config>(&*toml)
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?
+    } else {
+        Config::default()
     };
+
+    if let Ok(server) = env::var("AXON_SERVER") {
+        cfg.server = Some(server);
+    }
+    if let Ok(pass) = env::var("AXON_PASS") {
+        cfg.pass = Some(pass);
+    } else if let Some(ref cmd) = cfg.pass_command {
+        cfg.pass = Some(run_pass_command(cmd)?);
+    }
+
+    if cfg.confirm_quit && !cfg.confirm.contains(&Action::Quit) {
+        cfg.confirm.push(Action::Quit);
+    }
+
+    if cfg.autoconnect && cfg.server.is_none() {
+        return Err(format!(
+            "Invalid config {}: `autoconnect` is set, but neither `server` nor AXON_SERVER is \
+             specified",
+            path.display()
+        ));
+    }
+
+    Ok(cfg)
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -47,7 +95,197 @@ lazy_static! {
 pub struct Config {
     pub server: Option<String>,
     pub pass: Option<String>,
+    // Prints the password, instead of storing it in `pass`
+    #[serde(default)]
+    pub pass_command: Option<String>,
+    // Authenticates with a token issued by the server instead, if there is no password
+    #[serde(default)]
+    pub token: Option<String>,
     pub autoconnect: bool,
+    // Instances to pick from on startup, instead of typing their address
+    #[serde(default)]
+    pub servers: Vec<ServerConfig>,
+    #[serde(default)]
+    pub reconnect: bool,
+    #[serde(default)]
+    pub keys: KeyMap,
+    #[serde(default)]
+    pub size_units: SizeUnits,
+    // Show rates in bits instead of bytes per second
+    #[serde(default)]
+    pub rate_bits: bool,
+    // Below this terminal size only a warning is rendered
+    #[serde(default = "default_min_width")]
+    pub min_width: u16,
+    #[serde(default = "default_min_height")]
+    pub min_height: u16,
+    #[serde(default)]
+    pub theme: Theme,
+    // The same as listing `quit` in `confirm`
+    #[serde(default)]
+    pub confirm_quit: bool,
+    // The actions that ask before they are run, quitting only while torrents are transferring
+    #[serde(default, deserialize_with = "keymap::deserialize_confirm")]
+    pub confirm: Vec<Action>,
+    // The initial mode of the torrent filter
+    #[serde(default)]
+    pub filter_case_sensitive: bool,
+    // Label the stats of the torrent list in a line above it
+    #[serde(default)]
+    pub torrent_header: bool,
+    // Pin a row with the combined stats of the listed torrents above the list
+    #[serde(default)]
+    pub aggregate_row: bool,
+    // Shade the background of every other torrent row
+    #[serde(default)]
+    pub zebra: bool,
+    // Where too long torrent names are cut off
+    #[serde(default)]
+    pub name_truncation: Truncation,
+    // The stats shown right of the torrent names, in this order
+    #[serde(default = "default_torrent_columns")]
+    pub torrent_columns: Vec<TorrentColumn>,
+    // Replaces the name and columns of the torrent rows
+    #[serde(default, deserialize_with = "deserialize_row_format")]
+    pub row_format: Option<Template>,
+    // Show dates instead of how long ago they were
+    #[serde(default)]
+    pub absolute_times: bool,
+    // Restore the torrent filter of the last session
+    #[serde(default)]
+    pub remember_filter: bool,
+    // Warn if the server has less free space left
+    #[serde(default)]
+    pub min_free_space_mib: Option<u64>,
+    // Opens torrent paths instead of xdg-open/open
+    #[serde(default)]
+    pub open_command: Option<String>,
+    // Log to a file instead of stderr
+    #[serde(default)]
+    pub log: Option<LogConfig>,
+    #[serde(default)]
+    pub tls: TlsConfig,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogConfig {
+    pub path: String,
+    #[serde(default = "default_log_level", deserialize_with = "deserialize_level")]
+    pub level: LevelFilter,
+}
+
+/// A synapse instance in the `[[servers]]` array
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerConfig {
+    pub name: String,
+    pub url: String,
+    #[serde(default)]
+    pub pass: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// How the certificates of wss:// servers are verified
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    // A PEM certificate that is trusted in addition to the system's, e.g. a self-signed one
+    pub ca_cert: Option<String>,
+    // Don't verify certificates at all, which allows anyone to intercept the connection
+    pub danger_accept_invalid_certs: bool,
+}
+
+// Runs the command in a shell, its stdout without the trailing line break is the password
+fn run_pass_command(cmd: &str) -> Result<String, String> {
+    // Inherit the terminal, so that e.g. gpg can ask for the passphrase
+    let out = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("Failed to run `pass_command`: {}", e))?;
+    if !out.status.success() {
+        return Err(format!("`pass_command` failed: {}", out.status));
+    }
+
+    let pass = String::from_utf8(out.stdout)
+        .map_err(|_| "`pass_command` printed invalid UTF-8".to_owned())?;
+    let pass = pass.trim_end_matches(['\n', '\r']);
+    if pass.is_empty() {
+        return Err("`pass_command` printed no password".to_owned());
+    }
+    Ok(pass.to_owned())
+}
+
+fn default_log_level() -> LevelFilter {
+    LevelFilter::Info
+}
+
+fn deserialize_level<'de, D: Deserializer<'de>>(de: D) -> Result<LevelFilter, D::Error> {
+    let raw = String::deserialize(de)?;
+    raw.parse()
+        .map_err(|_| D::Error::custom(format!("invalid log level `{}`", raw)))
+}
+
+fn deserialize_row_format<'de, D: Deserializer<'de>>(de: D) -> Result<Option<Template>, D::Error> {
+    let raw = String::deserialize(de)?;
+    Template::parse(&raw).map(Some).map_err(D::Error::custom)
+}
+
+fn default_torrent_columns() -> Vec<TorrentColumn> {
+    vec![
+        TorrentColumn::Progress,
+        TorrentColumn::Eta,
+        TorrentColumn::Status,
+        TorrentColumn::Rates,
+        TorrentColumn::Ratio,
+        TorrentColumn::Lifetime,
+    ]
+}
+
+fn default_min_width() -> u16 {
+    40
+}
+
+fn default_min_height() -> u16 {
+    10
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeUnits {
+    // Powers of 1024, e.g. MiB
+    #[default]
+    Binary,
+    // Powers of 1000, e.g. MB
+    Decimal,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Truncation {
+    #[default]
+    End,
+    // Keeps the end, e.g. the file extension, visible
+    Middle,
+}
+
+/// A column of the stats block of the torrent list
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TorrentColumn {
+    // Percentage done
+    Progress,
+    Eta,
+    Status,
+    // Current rates and throttles
+    Rates,
+    Ratio,
+    // Total amount uploaded and downloaded
+    Lifetime,
 }
 
 impl Default for Config {
@@ -55,7 +293,32 @@ impl Default for Config {
         Config {
             server: None,
             pass: None,
+            pass_command: None,
+            token: None,
             autoconnect: false,
+            servers: Vec::new(),
+            reconnect: false,
+            keys: KeyMap::default(),
+            size_units: SizeUnits::default(),
+            rate_bits: false,
+            min_width: default_min_width(),
+            min_height: default_min_height(),
+            theme: Theme::default(),
+            confirm_quit: false,
+            confirm: Vec::new(),
+            filter_case_sensitive: false,
+            remember_filter: false,
+            absolute_times: false,
+            torrent_header: false,
+            aggregate_row: false,
+            zebra: false,
+            name_truncation: Truncation::End,
+            torrent_columns: default_torrent_columns(),
+            row_format: None,
+            min_free_space_mib: None,
+            open_command: None,
+            log: None,
+            tls: TlsConfig::default(),
         }
     }
 }
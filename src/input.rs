@@ -16,25 +16,75 @@
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
 use bytes::BytesMut;
-use log::debug;
+use log::{debug, warn};
 use termion::event::{self, Event, Key};
 use tokio::{codec, io, prelude::*};
 
 use std::io::Error;
 
+// Stop reading input after this many consecutive read errors, the terminal is likely gone
+const MAX_READ_ERRORS: usize = 10;
+
+// Enables/disables bracketed paste, pasted text is then enclosed in PASTE_START and PASTE_END
+pub const ENABLE_PASTE: &str = "\x1B[?2004h";
+pub const DISABLE_PASTE: &str = "\x1B[?2004l";
+const PASTE_START: &[u8] = b"\x1B[200~";
+const PASTE_END: &[u8] = b"\x1B[201~";
+
+#[derive(Debug)]
+pub enum Input {
+    Key(Key),
+    // Pasted text is delivered at once, so that it can't be mistaken for commands
+    Paste(String),
+}
+
 // FIXME: Due to `https://github.com/tokio-rs/tokio/issues/589` we currently need to handle stdin
 // in its own thread
-pub fn stream() -> impl Stream<Item = Key, Error = (String, String)> {
+pub fn stream() -> impl Stream<Item = Input, Error = (String, String)> {
     //codec::FramedRead::new(io::stdin(), InputCodec)
     //    .inspect(|key| debug!("Decoded: {:?}", key))
     //    .map_err(|e| ("Input".to_owned(), e.to_string()))
 
-    let (mut s, r) = futures::sync::mpsc::channel(5);
+    let (s, r) = futures::sync::mpsc::channel(5);
     std::thread::spawn(move || {
         use termion::input::TermRead;
-        for k in std::io::stdin().keys() {
-            debug!("Decoded: {:?}", k);
-            s.try_send(k.unwrap()).unwrap();
+        // Block while the channel is full, so that keys are not lost during fast input
+        let mut s = s.wait();
+        let mut errors = 0;
+        let mut paste: Option<String> = None;
+        for ev in std::io::stdin().events() {
+            debug!("Decoded: {:?}", ev);
+            let input = match ev {
+                Ok(Event::Unsupported(ref seq)) if &**seq == PASTE_START => {
+                    paste = Some(String::new());
+                    None
+                }
+                Ok(Event::Unsupported(ref seq)) if &**seq == PASTE_END => {
+                    paste.take().map(Input::Paste)
+                }
+                Ok(Event::Key(Key::Char(c))) if paste.is_some() => {
+                    paste.as_mut().unwrap().push(c);
+                    None
+                }
+                Ok(Event::Key(k)) if paste.is_none() => Some(Input::Key(k)),
+                Ok(_) => None,
+                Err(e) => {
+                    warn!("Failed to read input: {}", e);
+                    errors += 1;
+                    if errors == MAX_READ_ERRORS {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            errors = 0;
+
+            if let Some(input) = input {
+                if s.send(input).is_err() {
+                    // The receiver was dropped, i.e. axon is shutting down
+                    break;
+                }
+            }
         }
     });
 
@@ -0,0 +1,287 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use serde::de::{Deserialize, Deserializer, Error};
+use termion::event::Key;
+
+use std::collections::HashMap;
+
+/// Actions whose key can be rebound in the `[keys]` table of the config
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Up,
+    Down,
+    Left,
+    Right,
+    FocusUp,
+    FocusDown,
+    FocusLeft,
+    FocusRight,
+    Details,
+    Close,
+    Errors,
+    Filter,
+    ClearFilter,
+    Trackers,
+    Reannounce,
+    Peers,
+    Validate,
+    Sequential,
+    PriorityDown,
+    PriorityUp,
+    Move,
+    Rename,
+    Diagnostics,
+    Goto,
+    HalfPageUp,
+    HalfPageDown,
+    Connect,
+    SwitchField,
+    RevealPassword,
+    AbsoluteTimes,
+    NextError,
+    PrevError,
+    Search,
+    Totals,
+    Shrink,
+    Grow,
+    TrackerSort,
+    TokenAuth,
+    OpenPath,
+    Label,
+    Paste,
+}
+
+impl Action {
+    fn from_name(name: &str) -> Option<Action> {
+        Some(match name {
+            "quit" => Action::Quit,
+            "up" => Action::Up,
+            "down" => Action::Down,
+            "left" => Action::Left,
+            "right" => Action::Right,
+            "focus_up" => Action::FocusUp,
+            "focus_down" => Action::FocusDown,
+            "focus_left" => Action::FocusLeft,
+            "focus_right" => Action::FocusRight,
+            "details" => Action::Details,
+            "close" => Action::Close,
+            "errors" => Action::Errors,
+            "filter" => Action::Filter,
+            "clear_filter" => Action::ClearFilter,
+            "trackers" => Action::Trackers,
+            "reannounce" => Action::Reannounce,
+            "peers" => Action::Peers,
+            "validate" => Action::Validate,
+            "sequential" => Action::Sequential,
+            "priority_down" => Action::PriorityDown,
+            "priority_up" => Action::PriorityUp,
+            "move" => Action::Move,
+            "rename" => Action::Rename,
+            "diagnostics" => Action::Diagnostics,
+            "goto" => Action::Goto,
+            "half_page_up" => Action::HalfPageUp,
+            "half_page_down" => Action::HalfPageDown,
+            "connect" => Action::Connect,
+            "switch_field" => Action::SwitchField,
+            "reveal_password" => Action::RevealPassword,
+            "absolute_times" => Action::AbsoluteTimes,
+            "next_error" => Action::NextError,
+            "prev_error" => Action::PrevError,
+            "search" => Action::Search,
+            "totals" => Action::Totals,
+            "shrink" => Action::Shrink,
+            "grow" => Action::Grow,
+            "tracker_sort" => Action::TrackerSort,
+            "token_auth" => Action::TokenAuth,
+            "open_path" => Action::OpenPath,
+            "label" => Action::Label,
+            "paste" => Action::Paste,
+            _ => return None,
+        })
+    }
+
+    // Whether `confirm` can make the action ask before it is run
+    fn confirmable(self) -> bool {
+        matches!(
+            self,
+            Action::Quit
+                | Action::Reannounce
+                | Action::Validate
+                | Action::Sequential
+                | Action::PriorityDown
+                | Action::PriorityUp
+                | Action::OpenPath
+        )
+    }
+
+    fn default_key(self) -> Key {
+        match self {
+            Action::Quit => Key::Ctrl('q'),
+            Action::Up => Key::Char('k'),
+            Action::Down => Key::Char('j'),
+            Action::Left => Key::Char('h'),
+            Action::Right => Key::Char('l'),
+            Action::FocusUp => Key::Char('K'),
+            Action::FocusDown => Key::Char('J'),
+            Action::FocusLeft => Key::Char('H'),
+            Action::FocusRight => Key::Char('L'),
+            Action::Details => Key::Char('d'),
+            Action::Close => Key::Char('q'),
+            Action::Errors => Key::Char('e'),
+            Action::Filter => Key::Char('f'),
+            Action::ClearFilter => Key::Ctrl('f'),
+            Action::Trackers => Key::Char('t'),
+            Action::Reannounce => Key::Char('r'),
+            Action::Peers => Key::Char('P'),
+            Action::Validate => Key::Char('v'),
+            Action::Sequential => Key::Char('s'),
+            Action::PriorityDown => Key::Char('['),
+            Action::PriorityUp => Key::Char(']'),
+            Action::Move => Key::Char('m'),
+            Action::Rename => Key::Char('R'),
+            Action::Diagnostics => Key::Ctrl('g'),
+            Action::Goto => Key::Char(':'),
+            Action::HalfPageUp => Key::Ctrl('u'),
+            Action::HalfPageDown => Key::Ctrl('d'),
+            Action::Connect => Key::Char('\n'),
+            Action::SwitchField => Key::Char('\t'),
+            Action::RevealPassword => Key::Ctrl('r'),
+            Action::AbsoluteTimes => Key::Char('a'),
+            Action::NextError => Key::Char('n'),
+            Action::PrevError => Key::Char('N'),
+            Action::Search => Key::Char('/'),
+            Action::Totals => Key::Char('T'),
+            Action::Shrink => Key::Char('<'),
+            Action::Grow => Key::Char('>'),
+            Action::TrackerSort => Key::Char('o'),
+            Action::TokenAuth => Key::Ctrl('t'),
+            Action::OpenPath => Key::Char('o'),
+            Action::Label => Key::Char('b'),
+            Action::Paste => Key::Ctrl('v'),
+        }
+    }
+}
+
+/// Maps actions to keys, falling back to the default key of unmapped actions
+#[derive(Clone, Debug, Default)]
+pub struct KeyMap(HashMap<Action, Key>);
+
+impl KeyMap {
+    pub fn get(&self, action: Action) -> Key {
+        self.0
+            .get(&action)
+            .cloned()
+            .unwrap_or_else(|| action.default_key())
+    }
+
+    pub fn is(&self, action: Action, k: Key) -> bool {
+        self.get(action) == k
+    }
+
+    /// Like `is`, but keys that type a character don't match while typing, so that binding an
+    /// action to such a key doesn't make the character impossible to type
+    pub fn is_unless_typing(&self, action: Action, k: Key, typing: bool) -> bool {
+        self.is(action, k) && !(typing && types_char(k))
+    }
+}
+
+// Whether the key is typed into a text input, as opposed to e.g. moving its cursor
+fn types_char(k: Key) -> bool {
+    matches!(k, Key::Char(c) if !c.is_control())
+}
+
+impl<'de> Deserialize<'de> for KeyMap {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<KeyMap, D::Error> {
+        let raw = HashMap::<String, String>::deserialize(de)?;
+        let mut map = HashMap::with_capacity(raw.len());
+        for (name, key) in raw {
+            let action = Action::from_name(&name)
+                .ok_or_else(|| D::Error::custom(format!("unknown action `{}`", name)))?;
+            let key = parse_key(&key).map_err(|e| {
+                D::Error::custom(format!("invalid key for action `{}`: {}", name, e))
+            })?;
+            map.insert(action, key);
+        }
+        Ok(KeyMap(map))
+    }
+}
+
+/// Deserializes the names of the actions that ask for confirmation
+pub fn deserialize_confirm<'de, D: Deserializer<'de>>(de: D) -> Result<Vec<Action>, D::Error> {
+    Vec::<String>::deserialize(de)?
+        .iter()
+        .map(|name| match Action::from_name(name) {
+            Some(action) if action.confirmable() => Ok(action),
+            Some(_) => Err(D::Error::custom(format!(
+                "action `{}` can not ask for confirmation",
+                name
+            ))),
+            None => Err(D::Error::custom(format!("unknown action `{}`", name))),
+        })
+        .collect()
+}
+
+/// Parses a key description like `p`, `Ctrl-f`, `Alt-x`, `Enter`, or `F5`
+pub fn parse_key(s: &str) -> Result<Key, String> {
+    let single = |s: &str| {
+        let mut cs = s.chars();
+        match (cs.next(), cs.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    };
+
+    if let Some(c) = single(s) {
+        return Ok(Key::Char(c));
+    }
+
+    if let Some(i) = s.find('-') {
+        let (modifier, c) = (&s[..i], &s[i + 1..]);
+        let c = single(c)
+            .ok_or_else(|| format!("`{}-` must be followed by a single character", modifier))?;
+        return match &*modifier.to_lowercase() {
+            // The terminal can not distinguish between Ctrl-f and Ctrl-F
+            "c" | "ctrl" => Ok(Key::Ctrl(c.to_ascii_lowercase())),
+            "a" | "m" | "alt" | "meta" => Ok(Key::Alt(c)),
+            _ => Err(format!("unknown modifier `{}`", modifier)),
+        };
+    }
+
+    match &*s.to_lowercase() {
+        "enter" | "return" => Ok(Key::Char('\n')),
+        "tab" => Ok(Key::Char('\t')),
+        "space" => Ok(Key::Char(' ')),
+        "esc" | "escape" => Ok(Key::Esc),
+        "backspace" => Ok(Key::Backspace),
+        "delete" | "del" => Ok(Key::Delete),
+        "home" => Ok(Key::Home),
+        "end" => Ok(Key::End),
+        "pageup" | "pgup" => Ok(Key::PageUp),
+        "pagedown" | "pgdown" => Ok(Key::PageDown),
+        "up" => Ok(Key::Up),
+        "down" => Ok(Key::Down),
+        "left" => Ok(Key::Left),
+        "right" => Ok(Key::Right),
+        l if l.starts_with('f') => l[1..]
+            .parse::<u8>()
+            .ok()
+            .filter(|n| *n >= 1 && *n <= 12)
+            .map(Key::F)
+            .ok_or_else(|| format!("unknown key `{}`", s)),
+        _ => Err(format!("unknown key `{}`", s)),
+    }
+}
@@ -0,0 +1,147 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use chrono::Local;
+use env_logger::filter::{self, Filter};
+use lazy_static::lazy_static;
+use log::{LevelFilter, Log, Metadata, Record};
+use parking_lot::Mutex;
+
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+};
+
+use crate::config::LogConfig;
+
+// The maximum number of deferred records kept, older ones are dropped first
+const DEFERRED_CAPACITY: usize = 1000;
+
+lazy_static! {
+    static ref DEFERRED: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+}
+
+fn format(record: &Record) -> String {
+    format!(
+        "{} {:<5} {}: {}",
+        Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+        record.level(),
+        record.target(),
+        record.args()
+    )
+}
+
+// Appends the records up to a fixed level to a file, so that nothing is written to the terminal
+struct FileLogger {
+    file: Mutex<File>,
+    level: LevelFilter,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // There's nowhere to report failures to
+        let _ = writeln!(self.file.lock(), "{}", format(record));
+    }
+
+    fn flush(&self) {
+        let _ = self.file.lock().flush();
+    }
+}
+
+// Keeps the records filtered by `RUST_LOG` until the terminal is restored, since writing to
+// stderr would draw over the TUI
+struct DeferredLogger {
+    filter: Filter,
+}
+
+impl Log for DeferredLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+        let mut deferred = DEFERRED.lock();
+        if deferred.len() == DEFERRED_CAPACITY {
+            deferred.pop_front();
+        }
+        deferred.push_back(format(record));
+    }
+
+    fn flush(&self) {}
+}
+
+/// The most recent deferred records, oldest first
+pub fn deferred(n: usize) -> Vec<String> {
+    let deferred = DEFERRED.lock();
+    deferred
+        .iter()
+        .skip(deferred.len().saturating_sub(n))
+        .cloned()
+        .collect()
+}
+
+/// Writes the deferred records to stderr, which must only be done once the TUI is gone
+pub fn flush_deferred() {
+    let stderr = io::stderr();
+    let mut stderr = stderr.lock();
+    for line in DEFERRED.lock().drain(..) {
+        let _ = writeln!(stderr, "{}", line);
+    }
+}
+
+/// Logs to the configured file, or like env_logger configured by `RUST_LOG` to stderr. If stderr
+/// is the terminal, the records are deferred until axon exits.
+pub fn init(cfg: Option<&LogConfig>) -> Result<(), String> {
+    let cfg = match cfg {
+        Some(cfg) => cfg,
+        None if !termion::is_tty(&io::stderr()) => {
+            env_logger::init();
+            return Ok(());
+        }
+        None => {
+            let filter = filter::Builder::from_env("RUST_LOG").build();
+            let level = filter.filter();
+            log::set_boxed_logger(Box::new(DeferredLogger { filter }))
+                .map_err(|e| e.to_string())?;
+            log::set_max_level(level);
+            return Ok(());
+        }
+    };
+
+    let path = shellexpand::full(&cfg.path).map_err(|e| e.to_string())?;
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&*path)
+        .map_err(|e| format!("Failed to open log file {}: {}", path, e))?;
+    log::set_boxed_logger(Box::new(FileLogger {
+        file: Mutex::new(file),
+        level: cfg.level,
+    }))
+    .map_err(|e| e.to_string())?;
+    log::set_max_level(cfg.level);
+    Ok(())
+}
@@ -24,17 +24,57 @@ compile_error!(
 
 mod config;
 mod input;
+mod keymap;
+mod logger;
 mod rpc;
+mod state;
 mod tui;
 mod utils;
 
 use futures::sync::mpsc;
 use log::{info, warn};
+use termion::{cursor, screen};
+
+use std::{mem, panic, process, sync::atomic::Ordering};
 
 use crate::{config::CONFIG, tui::view};
 
+// Restores the terminal before the panic message is printed, otherwise a panic while the TUI is
+// shown leaves the terminal in raw mode on the alternate screen
+fn set_panic_hook() {
+    let mut termios: libc::termios = unsafe { mem::zeroed() };
+    let saved = unsafe { libc::tcgetattr(libc::STDOUT_FILENO, &mut termios) } == 0;
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if view::ACTIVE.swap(false, Ordering::SeqCst) {
+            if saved {
+                unsafe { libc::tcsetattr(libc::STDOUT_FILENO, libc::TCSANOW, &termios) };
+            }
+            // Stdout is bypassed, since its lock may be held by the panicking thread
+            let restore = format!(
+                "{}{}{}",
+                input::DISABLE_PASTE,
+                screen::ToMainScreen,
+                cursor::Show
+            );
+            unsafe {
+                libc::write(
+                    libc::STDOUT_FILENO,
+                    restore.as_ptr() as *const _,
+                    restore.len(),
+                )
+            };
+        }
+        default(info);
+    }));
+}
+
 fn main() {
-    env_logger::init();
+    set_panic_hook();
+    if let Err(e) = logger::init(CONFIG.log.as_ref()) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
     warn!("Do not share this log publicly without first removing sensitive information: Any address connected to, any decoded key presses while entering password or other sensitive information!\n\n");
 
     let (mut urls_s, urls_r) = mpsc::channel(1);;
@@ -42,13 +82,13 @@ fn main() {
 
     if CONFIG.autoconnect {
         info!("Autoconnecting");
+        let auth = rpc::Auth::from_config(CONFIG.pass.as_ref(), CONFIG.token.as_ref())
+            .unwrap_or_else(|| rpc::Auth::Password(String::new()));
         urls_s
-            .try_send((
-                CONFIG.server.clone().unwrap(),
-                CONFIG.pass.clone().unwrap_or_default(),
-            ))
+            .try_send((CONFIG.server.clone().unwrap(), auth))
             .unwrap();
     }
 
     tokio::run(view::run(urls_s, conns));
+    logger::flush_deferred();
 }
@@ -15,34 +15,97 @@
 // You should have received a copy of the GNU General Public License
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
+use chrono::{DateTime, Utc};
 use futures::sync::mpsc;
+use lazy_static::lazy_static;
 use log::{debug, trace, warn};
+use native_tls::{Certificate, TlsConnector};
 use parking_lot::Mutex;
 use serde_json;
 use synapse_rpc::{
     self,
-    message::{CMessage, SMessage},
+    message::{CMessage, SMessage, Version},
 };
-use tokio::{net::TcpStream, prelude::*};
-use tokio_tungstenite::{self, tungstenite::Message as WsMessage, MaybeTlsStream, WebSocketStream};
-use url::Url;
+use tokio::{net::TcpStream, prelude::*, timer};
+use tokio_tungstenite::{
+    self,
+    stream::Stream as StreamSwitcher,
+    tungstenite::{
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Error as WsError, Message as WsMessage,
+    },
+    MaybeTlsStream, WebSocketStream,
+};
+use url::{form_urlencoded, Url};
 
 use std::{
+    collections::HashSet,
     error::Error,
+    fs::File,
+    io::Read,
+    mem,
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use crate::config::CONFIG;
+
 static SERIAL: AtomicUsize = AtomicUsize::new(0);
 
-pub type WsSink = Arc<Mutex<stream::SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>>>>;
+lazy_static! {
+    static ref STATS: Mutex<Stats> = Mutex::new(Stats::default());
+}
+
+/// Statistics about the current connection, for debugging
+#[derive(Clone, Default)]
+pub struct Stats {
+    pub connected: Option<DateTime<Utc>>,
+    // The number of individually subscribed resources
+    pub subscribed: usize,
+    // The serials of the active filter subscriptions
+    pub filters: HashSet<u64>,
+    pub rtt: Option<Duration>,
+    // The message whose reply is used to measure the round-trip time
+    probe: Option<(u64, Instant)>,
+}
+
+pub fn stats() -> Stats {
+    STATS.lock().clone()
+}
+
+/// The serial the next message will be sent with
+pub fn current_serial() -> u64 {
+    SERIAL.load(Ordering::Relaxed) as _
+}
+
+type RawSink = Arc<Mutex<stream::SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>>>>;
+
+/// Sends messages to the server, this allows using components without a connection
+pub trait MessageSink: Send + Sync {
+    fn send(&self, msg: CMessage);
+}
+
+pub type WsSink = Arc<dyn MessageSink>;
+
+// The sink of an established connection
+struct Connection(RawSink);
+
+impl MessageSink for Connection {
+    fn send(&self, msg: CMessage) {
+        send_raw(
+            Arc::clone(&self.0),
+            WsMessage::Text(serde_json::to_string(&msg).unwrap()),
+        );
+    }
+}
 
 pub enum Item {
-    Idle,
     Msg(SMessage<'static>),
+    // The server's minor version differs, which the user may choose to ignore
+    VersionMismatch(Version),
 }
 
 pub fn next_serial() -> u64 {
@@ -51,13 +114,67 @@ pub fn next_serial() -> u64 {
 
 pub fn send(sink: &WsSink, msg: CMessage) {
     debug!("Sending {:#?}", msg);
-    send_raw(
-        Arc::clone(sink),
-        WsMessage::Text(serde_json::to_string(&msg).unwrap()),
-    );
+    track_sent(&msg);
+    sink.send(msg);
+}
+
+fn track_sent(msg: &CMessage) {
+    let mut stats = STATS.lock();
+    let serial = match *msg {
+        CMessage::Subscribe { serial, ref ids } => {
+            stats.subscribed += ids.len();
+            serial
+        }
+        CMessage::Unsubscribe { serial, ref ids } => {
+            stats.subscribed = stats.subscribed.saturating_sub(ids.len());
+            serial
+        }
+        // Subscribing with the serial of an existing filter replaces it
+        CMessage::FilterSubscribe { serial, .. } => {
+            stats.filters.insert(serial);
+            serial
+        }
+        CMessage::FilterUnsubscribe { filter_serial, .. } => {
+            stats.filters.remove(&filter_serial);
+            return;
+        }
+        // Other messages are not guaranteed to be replied to
+        _ => return,
+    };
+    if stats.probe.is_none() {
+        stats.probe = Some((serial, Instant::now()));
+    }
 }
 
-fn send_raw(sink: WsSink, msg: WsMessage) {
+// Passes the message through, so that it can be used in a chain
+fn track_received(msg: SMessage) -> SMessage {
+    let serial = match msg {
+        SMessage::UpdateResources {
+            serial: Some(serial),
+            ..
+        }
+        | SMessage::ResourcesExtant { serial, .. } => serial,
+        SMessage::UnknownResource(ref e)
+        | SMessage::InvalidResource(ref e)
+        | SMessage::InvalidRequest(ref e)
+        | SMessage::PermissionDenied(ref e)
+        | SMessage::TransferFailed(ref e) => match e.serial {
+            Some(serial) => serial,
+            None => return msg,
+        },
+        _ => return msg,
+    };
+    let mut stats = STATS.lock();
+    if let Some((probe, sent)) = stats.probe {
+        if probe == serial {
+            stats.rtt = Some(sent.elapsed());
+            stats.probe = None;
+        }
+    }
+    msg
+}
+
+fn send_raw(sink: RawSink, msg: WsMessage) {
     let mut msg = Some(msg);
     tokio::spawn(future::poll_fn(move || {
         if SERIAL.load(Ordering::Acquire) == 0 {
@@ -77,8 +194,156 @@ fn send_raw(sink: WsSink, msg: WsMessage) {
     }));
 }
 
+// The port synapse listens on by default
+const DEFAULT_PORT: u16 = 8412;
+
+// A ping is sent if nothing was received for this many seconds
+const PING_INTERVAL_SECS: u64 = 15;
+// The connection is considered lost if nothing was received for this many seconds
+const ACTIVITY_TIMEOUT_SECS: u64 = 45;
+// Newly extant resources are collected for this long, and then subscribed to at once
+const SUBSCRIBE_DELAY_MS: u64 = 20;
+
+// The kind of errors caused by the connection failing, rather than by the server rejecting it or
+// misbehaving
+const CONNECTION_ERROR: &str = "Connection";
+
+/// Whether the connection failed, so that retrying it may succeed, unlike if e.g. the password
+/// was rejected
+pub fn is_connection_error(e: &(String, String)) -> bool {
+    e.0 == CONNECTION_ERROR
+}
+
+fn error_kind(e: &WsError) -> &'static str {
+    match *e {
+        WsError::Io(_) | WsError::ConnectionClosed => CONNECTION_ERROR,
+        _ => "RPC",
+    }
+}
+
+/// Completes a server address like `host` or `host:port` to the websocket URL to connect to
+pub fn server_url(server: &str) -> Result<Url, String> {
+    let server = server.trim();
+    let full = if server.contains("://") {
+        server.to_owned()
+    } else {
+        format!("ws://{}", server)
+    };
+    let mut url = Url::parse(&full).map_err(|e| e.to_string())?;
+    if url.scheme() != "ws" && url.scheme() != "wss" {
+        return Err(format!(
+            "unsupported scheme `{}`, expected ws or wss",
+            url.scheme()
+        ));
+    }
+    if !has_port(&full) {
+        url.set_port(Some(DEFAULT_PORT))
+            .map_err(|_| "the URL can not have a port".to_owned())?;
+    }
+    Ok(url)
+}
+
+// Whether the URL specifies a port, `Url::port` can't tell since it hides the scheme's default
+fn has_port(url: &str) -> bool {
+    let rest = &url[url.find("://").map(|i| i + 3).unwrap_or(0)..];
+    let authority = &rest[..rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+    let host = &authority[authority.rfind('@').map(|i| i + 1).unwrap_or(0)..];
+    // IPv6 addresses contain colons, but are enclosed in brackets
+    let host = &host[host.rfind(']').unwrap_or(0)..];
+    host.find(':').map(|i| i + 1 < host.len()).unwrap_or(false)
+}
+
+// Builds the connector for wss:// servers from the `[tls]` config
+fn tls_connector() -> Result<TlsConnector, String> {
+    let mut builder = TlsConnector::builder();
+    if let Some(ref path) = CONFIG.tls.ca_cert {
+        let path = shellexpand::tilde(path);
+        let mut pem = Vec::new();
+        File::open(&*path)
+            .and_then(|mut f| f.read_to_end(&mut pem))
+            .map_err(|e| format!("Failed to read CA certificate {}: {}", path, e))?;
+        let cert = Certificate::from_pem(&pem)
+            .map_err(|e| format!("Invalid CA certificate {}: {}", path, e))?;
+        builder.add_root_certificate(cert);
+    }
+    if CONFIG.tls.danger_accept_invalid_certs {
+        warn!(
+            "TLS CERTIFICATE VERIFICATION IS DISABLED, the connection and password can be \
+             intercepted"
+        );
+        builder.danger_accept_invalid_certs(true);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+// Like `tokio_tungstenite::connect_async`, which can't be given a TLS connector
+fn connect(
+    url: Url,
+    tls: Option<TlsConnector>,
+) -> impl Future<Item = WebSocketStream<MaybeTlsStream<TcpStream>>, Error = WsError> {
+    let host = url.host_str().unwrap_or_default().to_owned();
+    let port = url.port_or_known_default().unwrap_or(DEFAULT_PORT);
+    tokio_dns::TcpStream::connect((&*host, port))
+        .map_err(WsError::Io)
+        .and_then(move |stream| match tls {
+            Some(tls) => future::Either::A(
+                tokio_tls::TlsConnector::from(tls)
+                    .connect(&host, stream)
+                    .map(StreamSwitcher::Tls)
+                    .map_err(WsError::Tls),
+            ),
+            None => future::Either::B(future::ok(StreamSwitcher::Plain(stream))),
+        })
+        .and_then(move |stream| tokio_tungstenite::client_async(url, stream))
+        .map(|(stream, _)| stream)
+}
+
+/// What a connection is authenticated with
+#[derive(Clone, PartialEq)]
+pub enum Auth {
+    Password(String),
+    // Issued by some deployments in place of the password
+    Token(String),
+}
+
+impl Auth {
+    /// The configured password, or the token if there is no password
+    pub fn from_config(pass: Option<&String>, token: Option<&String>) -> Option<Auth> {
+        match (pass, token) {
+            (Some(pass), _) => Some(Auth::Password(pass.clone())),
+            (None, Some(token)) => Some(Auth::Token(token.clone())),
+            (None, None) => None,
+        }
+    }
+
+    pub fn secret(&self) -> &str {
+        match *self {
+            Auth::Password(ref s) | Auth::Token(ref s) => s,
+        }
+    }
+
+    // The query parameter the secret is passed as
+    fn param(&self) -> &'static str {
+        match *self {
+            Auth::Password(_) => "password",
+            Auth::Token(_) => "token",
+        }
+    }
+}
+
+// Removes the secret from an error message, in case it includes the URL that was connected to
+fn scrub_secret(msg: String, auth: &Auth) -> String {
+    if auth.secret().is_empty() {
+        return msg;
+    }
+    let pair = form_urlencoded::Serializer::new(String::new())
+        .append_pair(auth.param(), auth.secret())
+        .finish();
+    msg.replace(&pair, &format!("{}=***", auth.param()))
+}
+
 pub fn connections(
-    urls: mpsc::Receiver<(String, String)>,
+    urls: mpsc::Receiver<(String, Auth)>,
 ) -> impl Stream<
     Item = impl Future<
         Item = (WsSink, impl Stream<Item = Item, Error = (String, String)>),
@@ -87,33 +352,53 @@ pub fn connections(
     Error = (String, String),
 > {
     urls.map_err(|_| unreachable!())
-        .and_then(move |(server, pass)| {
-            let mut url = Url::parse(&server).map_err(|e| ("Url".into(), e.to_string()))?;
+        .and_then(move |(server, auth)| {
+            let mut url = server_url(&server).map_err(|e| ("Url".into(), e))?;
+            // synapse only accepts the secret as a query parameter, which must thus never be
+            // logged or shown, see `scrub_secret`
             url.query_pairs_mut()
-                .append_pair("password", &pass)
+                .append_pair(auth.param(), auth.secret())
                 .finish();
+            let tls = if url.scheme() == "wss" {
+                Some(tls_connector().map_err(|e| ("TLS".to_owned(), e))?)
+            } else {
+                None
+            };
             trace!("Should connect to {:?}", url.origin());
 
-            Ok(tokio_tungstenite::connect_async(url)
+            Ok(connect(url, tls)
                 .timeout(Duration::from_secs(10))
-                .map_err(|e| {
-                    (
-                        "RPC".to_string(),
-                        if e.is_timer() {
-                            "Timeout connecting to server (10s)".to_owned()
-                        } else {
-                            format!("{:?}", e.into_inner().unwrap())
-                        },
-                    )
+                .map_err(move |e| {
+                    if e.is_timer() {
+                        return (
+                            CONNECTION_ERROR.to_owned(),
+                            "Timeout connecting to server (10s)".to_owned(),
+                        );
+                    }
+                    let e = e.into_inner().unwrap();
+                    let kind = error_kind(&e);
+                    let text = match e {
+                        WsError::Http(401) | WsError::Http(403) => {
+                            format!("The server rejected the {}", auth.param())
+                        }
+                        e => scrub_secret(format!("{:?}", e), &auth),
+                    };
+                    (kind.to_owned(), text)
                 })
-                .map(move |(stream, _)| {
+                .map(move |stream| {
                     trace!("Connected");
+                    *STATS.lock() = Stats {
+                        connected: Some(Utc::now()),
+                        ..Stats::default()
+                    };
 
-                    let (sink, stream) = stream.split();
-                    let sink = Arc::new(Mutex::new(sink));
+                    let (raw, stream) = stream.split();
+                    let raw = Arc::new(Mutex::new(raw));
+                    let sink: WsSink = Arc::new(Connection(Arc::clone(&raw)));
                     let stream = handle_connection(
+                        raw,
                         Arc::clone(&sink),
-                        stream.map_err(|e| ("RPC".into(), e.to_string())),
+                        stream.map_err(|e| (error_kind(&e).to_owned(), e.to_string())),
                     );
                     (sink, stream)
                 }))
@@ -121,46 +406,102 @@ pub fn connections(
 }
 
 fn handle_connection(
+    raw: RawSink,
     sink: WsSink,
     stream: impl Stream<Item = WsMessage, Error = (String, String)>,
 ) -> impl Stream<Item = Item, Error = (String, String)> {
+    let last_activity = Arc::new(Mutex::new(Instant::now()));
+    let last_activity2 = Arc::clone(&last_activity);
+    let raw2 = Arc::clone(&raw);
+
+    // Without this, a connection that silently died would never be noticed
+    let interval = Duration::from_secs(PING_INTERVAL_SECS);
+    let keepalive = timer::Interval::new(Instant::now() + interval, interval)
+        .map_err(|e| ("Timer".to_owned(), e.to_string()))
+        .and_then(move |_| {
+            let idle = last_activity2.lock().elapsed();
+            if idle >= Duration::from_secs(ACTIVITY_TIMEOUT_SECS) {
+                warn!(
+                    "No activity for {:?}, assuming the connection is lost",
+                    idle
+                );
+                Err((
+                    CONNECTION_ERROR.to_owned(),
+                    format!("The server did not respond for {}s", idle.as_secs()),
+                ))
+            } else {
+                if idle >= interval {
+                    trace!("Sending keepalive ping");
+                    send_raw(Arc::clone(&raw2), WsMessage::Ping(Vec::new()));
+                }
+                Ok(None)
+            }
+        })
+        .filter_map(|item| item);
+
+    // The ids that will be subscribed to once the delay is over
+    let extant = Arc::new(Mutex::new(Vec::new()));
+
     stream
+        .inspect(move |_| *last_activity.lock() = Instant::now())
         .and_then(move |msg| match msg {
             WsMessage::Ping(p) => {
-                send_raw(Arc::clone(&sink), WsMessage::Pong(p));
-                Ok(Item::Idle)
+                send_raw(Arc::clone(&raw), WsMessage::Pong(p));
+                Ok(None)
             }
-            WsMessage::Text(s) => match serde_json::from_str::<SMessage>(&s) {
+            WsMessage::Text(s) => match serde_json::from_str::<SMessage>(&s).map(track_received) {
                 Err(e) => Err(("RPC".to_owned(), e.description().to_string())),
                 Ok(SMessage::ResourcesExtant { ids, .. }) => {
                     trace!("ResourcesExtant: {:#?}", ids);
-                    send(
-                        &sink,
-                        CMessage::Subscribe {
-                            serial: next_serial(),
-                            ids: ids.iter().map(|id| (&**id).to_string()).collect(),
-                        },
-                    );
-                    // FIXME: This shouldn't be necessary, but without it we miss the reply - why?
-                    task::current().notify();
-                    Ok(Item::Idle)
+                    let mut pending = extant.lock();
+                    if pending.is_empty() {
+                        let (extant, sink) = (Arc::clone(&extant), Arc::clone(&sink));
+                        tokio::spawn(
+                            timer::Delay::new(
+                                Instant::now() + Duration::from_millis(SUBSCRIBE_DELAY_MS),
+                            )
+                            .then(move |_| {
+                                let ids = mem::take(&mut *extant.lock());
+                                // All of them may have been removed in the meantime
+                                if !ids.is_empty() {
+                                    send(
+                                        &sink,
+                                        CMessage::Subscribe {
+                                            serial: next_serial(),
+                                            ids,
+                                        },
+                                    );
+                                }
+                                Ok(())
+                            }),
+                        );
+                    }
+                    pending.extend(ids.iter().map(|id| (&**id).to_string()));
+                    Ok(None)
                 }
                 Ok(SMessage::ResourcesRemoved { serial, ids }) => {
                     trace!("ResourcesRemoved: {:#?}", ids);
-                    send(
-                        &sink,
-                        CMessage::Unsubscribe {
-                            serial: next_serial(),
-                            ids: ids.clone(),
-                        },
-                    );
-                    Ok(Item::Msg(SMessage::ResourcesRemoved { serial, ids }))
+                    // Resources removed before they were subscribed to are not subscribed at all
+                    let mut pending = extant.lock();
+                    let subscribed: Vec<_> = ids
+                        .iter()
+                        .filter(|id| !pending.contains(id))
+                        .cloned()
+                        .collect();
+                    pending.retain(|id| !ids.contains(id));
+                    if !subscribed.is_empty() {
+                        send(
+                            &sink,
+                            CMessage::Unsubscribe {
+                                serial: next_serial(),
+                                ids: subscribed,
+                            },
+                        );
+                    }
+                    Ok(Some(Item::Msg(SMessage::ResourcesRemoved { serial, ids })))
                 }
                 Ok(SMessage::RpcVersion(ver)) => {
-                    if ver.major != synapse_rpc::MAJOR_VERSION
-                        || (ver.minor != synapse_rpc::MINOR_VERSION
-                            && synapse_rpc::MAJOR_VERSION == 0)
-                    {
+                    if ver.major != synapse_rpc::MAJOR_VERSION {
                         warn!("RPC version mismatch");
                         Err((
                             "RPC".to_string(),
@@ -172,19 +513,150 @@ fn handle_connection(
                                 synapse_rpc::MINOR_VERSION
                             ),
                         ))
+                    } else if ver.minor != synapse_rpc::MINOR_VERSION
+                        && synapse_rpc::MAJOR_VERSION == 0
+                    {
+                        warn!("RPC minor version mismatch");
+                        Ok(Some(Item::VersionMismatch(ver)))
                     } else {
-                        Ok(Item::Msg(SMessage::RpcVersion(ver)))
+                        Ok(Some(Item::Msg(SMessage::RpcVersion(ver))))
                     }
                 }
                 Ok(msg) => {
                     trace!("Received: {:#?}", msg);
-                    Ok(Item::Msg(msg))
+                    Ok(Some(Item::Msg(msg)))
                 }
             },
-            _ => unreachable!(),
+            WsMessage::Close(frame) => {
+                // The server may be back soon, but e.g. a rejected password won't be accepted
+                let kind = match frame.as_ref().map(|f| f.code) {
+                    None
+                    | Some(CloseCode::Away)
+                    | Some(CloseCode::Restart)
+                    | Some(CloseCode::Again) => CONNECTION_ERROR,
+                    _ => "Disconnected",
+                };
+                let text = close_reason(frame);
+                warn!("Connection closed by server: {}", text);
+                Err((kind.to_owned(), text))
+            }
+            WsMessage::Pong(_) => Ok(None),
+            WsMessage::Binary(_) => Err((
+                "RPC".to_owned(),
+                "Received an unexpected binary message".to_owned(),
+            )),
         })
+        // Yielding nothing for handled messages would leave the consumer waiting on a stream that
+        // is not guaranteed to notify it again, so they are skipped here instead
+        .filter_map(|item| item)
+        // The stream ends if the connection was closed without a close frame, which the
+        // keepalive would only notice much later
+        .chain(stream::once(Err((
+            CONNECTION_ERROR.to_owned(),
+            "The server closed the connection".to_owned(),
+        ))))
+        .select(keepalive)
         .or_else(move |v| {
             SERIAL.store(0, Ordering::Release);
+            STATS.lock().connected = None;
             Err(v)
         })
 }
+
+// Describes why the server closed the connection, the codes are defined in RFC 6455
+fn close_reason(frame: Option<CloseFrame>) -> String {
+    let frame = match frame {
+        Some(frame) => frame,
+        None => return "The server closed the connection".to_owned(),
+    };
+    let text = match frame.code {
+        CloseCode::Away => "The server is shutting down",
+        CloseCode::Policy => "The server refused the connection, e.g. due to a wrong password",
+        CloseCode::Protocol | CloseCode::Unsupported | CloseCode::Invalid => {
+            "The server did not understand a message"
+        }
+        CloseCode::Size => "A message was too big for the server",
+        CloseCode::Error => "The server encountered an internal error",
+        CloseCode::Restart => "The server is restarting",
+        CloseCode::Again => "The server is overloaded, try again later",
+        _ => "The server closed the connection",
+    };
+    if frame.reason.is_empty() {
+        format!("{} (code {})", text, u16::from(frame.code))
+    } else {
+        format!(
+            "{}: {} (code {})",
+            text,
+            frame.reason,
+            u16::from(frame.code)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> String {
+        server_url(s).unwrap().into_string()
+    }
+
+    #[test]
+    fn completes_server_urls() {
+        assert_eq!(url("localhost"), "ws://localhost:8412/");
+        assert_eq!(url("localhost:9000"), "ws://localhost:9000/");
+        assert_eq!(url(" 127.0.0.1 "), "ws://127.0.0.1:8412/");
+        assert_eq!(url("[::1]"), "ws://[::1]:8412/");
+        assert_eq!(url("[::1]:9000"), "ws://[::1]:9000/");
+        assert_eq!(url("wss://example.com"), "wss://example.com:8412/");
+        assert_eq!(
+            url("wss://user@example.com/rpc"),
+            "wss://user@example.com:8412/rpc"
+        );
+        // The scheme's default port isn't replaced with synapse's, but the URL parser drops it
+        assert_eq!(url("ws://example.com:80"), "ws://example.com/");
+    }
+
+    #[test]
+    fn scrubs_secrets() {
+        for auth in &[
+            Auth::Password("hack me&".to_owned()),
+            Auth::Token("hack me&".to_owned()),
+        ] {
+            let mut url = server_url("localhost").unwrap();
+            url.query_pairs_mut()
+                .append_pair(auth.param(), auth.secret())
+                .finish();
+            let msg = format!("Failed to connect to {}", url);
+            assert_eq!(
+                scrub_secret(msg, auth),
+                format!(
+                    "Failed to connect to ws://localhost:8412/?{}=***",
+                    auth.param()
+                )
+            );
+        }
+        assert_eq!(
+            scrub_secret("Timeout".to_owned(), &Auth::Password(String::new())),
+            "Timeout"
+        );
+    }
+
+    #[test]
+    fn retries_only_connection_errors() {
+        let kind = |e| (error_kind(&e).to_owned(), String::new());
+        assert!(is_connection_error(&kind(WsError::ConnectionClosed)));
+        assert!(is_connection_error(&kind(WsError::Io(
+            std::io::ErrorKind::ConnectionReset.into()
+        ))));
+        assert!(!is_connection_error(&kind(WsError::Http(401))));
+    }
+
+    #[test]
+    fn rejects_invalid_server_urls() {
+        assert!(server_url("").is_err());
+        assert!(server_url("ws://:8412").is_err());
+        assert!(server_url("localhost:port").is_err());
+        assert!(server_url("http://localhost").is_err());
+    }
+}
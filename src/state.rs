@@ -0,0 +1,84 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
+
+// The number of recently used servers that are remembered
+const MAX_SERVERS: usize = 10;
+
+fn path() -> PathBuf {
+    let path = shellexpand::full("$XDG_STATE_HOME/axon/state.json")
+        .unwrap_or_else(|_| shellexpand::tilde("~/.local/state/axon/state.json"));
+    PathBuf::from(&*path)
+}
+
+/// State that is persisted across runs, unlike the config it is written by axon itself
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct State {
+    // Most recently used first, never contains passwords
+    pub servers: Vec<String>,
+    // The torrent filter of the last session, if `remember_filter` is set
+    pub filter: String,
+    // The sizes of the trackers sidebar and details pane, if they were changed
+    pub trackers_width: Option<f32>,
+    pub details_height: Option<u16>,
+}
+
+impl State {
+    pub fn load() -> State {
+        let path = path();
+        // Tests must neither depend on nor change the user's state
+        if cfg!(test) || !path.exists() {
+            return State::default();
+        }
+        File::open(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|f| serde_json::from_reader(f).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to load state from {}: {}", path.display(), e);
+                State::default()
+            })
+    }
+
+    pub fn save(&self) {
+        if cfg!(test) {
+            return;
+        }
+        let path = path();
+        if let Err(e) = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .map_err(|e| e.to_string())
+            .and_then(|_| File::create(&path).map_err(|e| e.to_string()))
+            .and_then(|f| serde_json::to_writer(f, self).map_err(|e| e.to_string()))
+        {
+            warn!("Failed to save state to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Remembers the server as the most recently used one
+    pub fn push_server(&mut self, server: String) {
+        self.servers.retain(|s| *s != server);
+        self.servers.insert(0, server);
+        self.servers.truncate(MAX_SERVERS);
+    }
+}
@@ -13,14 +13,44 @@
 // You should have received a copy of the GNU General Public License
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod notify;
 pub mod panels;
+mod screen;
 pub mod view;
 pub mod widgets;
 
 use synapse_rpc::message::SMessage;
 use termion::event::Key;
 
-pub trait Component: Renderable + HandleInput + HandleRpc + Send + Sync {}
+use std::time::Instant;
+
+use crate::{config::CONFIG, keymap::Action, rpc};
+
+pub trait Component: Renderable + HandleInput + HandleRpc + Send + Sync {
+    // Whether any torrent shown by the component is currently up- or downloading
+    fn transferring(&self) -> bool {
+        false
+    }
+    // Called before the component is dropped for good, to undo e.g. subscriptions. Not done on
+    // drop, since components are cloned to show overlays above them
+    fn teardown(&mut self) {}
+    // Drops state that is only shown for a while, e.g. highlights, and returns whether that
+    // changed what is rendered
+    fn expire(&mut self) -> bool {
+        false
+    }
+    // Whether a text input has focus, keys that type a character are then not used as bindings
+    fn typing(&self) -> bool {
+        false
+    }
+    // Called when the view starts connecting, with the time the attempt was started, and with
+    // None once the attempt failed
+    fn connecting(&mut self, _since: Option<Instant>) {}
+    // Runs the action after the user confirmed it, without asking again
+    fn input_confirmed(&mut self, action: Action, width: u16, height: u16) -> InputResult {
+        self.input(CONFIG.keys.get(action), width, height)
+    }
+}
 
 pub trait Renderable: Send {
     fn name(&self) -> String {
@@ -33,6 +63,10 @@ pub trait Renderable: Send {
 
 pub trait HandleInput: Send {
     fn input(&mut self, k: Key, width: u16, height: u16) -> InputResult;
+    // Pasted text is ignored by components without a text input
+    fn paste(&mut self, _text: &str, _width: u16, _height: u16) -> InputResult {
+        InputResult::Rerender
+    }
 }
 
 pub trait HandleRpc: Send {
@@ -41,9 +75,11 @@ pub trait HandleRpc: Send {
 
 pub enum InputResult {
     Close,
-    ConnectWith(String, String),
+    ConnectWith(String, rpc::Auth),
     Rerender,
     ReplaceWith(Box<Component>),
     // A key was not used by any component below the current one
     Key(Key),
+    // Quitting was confirmed, the view disconnects or exits
+    Quit,
 }
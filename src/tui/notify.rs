@@ -0,0 +1,59 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// The maximum number of notifications kept, older ones are dropped first
+const CAPACITY: usize = 8;
+
+lazy_static! {
+    static ref QUEUE: Mutex<VecDeque<(String, Instant)>> =
+        Mutex::new(VecDeque::with_capacity(CAPACITY));
+}
+
+/// Shows a transient message in the footer line for a few seconds
+pub fn push<T: Into<String>>(text: T) {
+    let mut queue = QUEUE.lock();
+    if queue.len() == CAPACITY {
+        queue.pop_front();
+    }
+    queue.push_back((text.into(), Instant::now() + Duration::from_secs(3)));
+}
+
+/// The newest notification that has not expired yet
+pub fn current() -> Option<String> {
+    let now = Instant::now();
+    QUEUE
+        .lock()
+        .iter()
+        .rev()
+        .find(|(_, expiry)| *expiry > now)
+        .map(|(text, _)| text.clone())
+}
+
+/// Removes expired notifications, returns whether any were removed
+pub fn expire() -> bool {
+    let now = Instant::now();
+    let mut queue = QUEUE.lock();
+    let len = queue.len();
+    queue.retain(|&(_, expiry)| expiry > now);
+    len != queue.len()
+}
@@ -13,37 +13,97 @@
 // You should have received a copy of the GNU General Public License
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
+use chrono::{DateTime, Utc};
 use natord;
+use serde_json::json;
 use synapse_rpc::{
     message::{CMessage, SMessage},
-    resource::{Resource, ResourceKind, SResourceUpdate, Server, Torrent, Tracker},
+    resource::{
+        CResourceUpdate, Resource, ResourceKind, SResourceUpdate, Server, Status, Strategy,
+        Torrent, Tracker,
+    },
 };
-use termion::event::Key;
+use termion::{event::Key, style};
+use url::Url;
 
-use std::cmp::{self, Ordering};
+use std::{
+    borrow::Cow,
+    cmp::{self, Ordering},
+    collections::{HashMap, HashSet, VecDeque},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::{
+    config::{TorrentColumn, Truncation, CONFIG},
+    keymap::Action,
     rpc,
-    tui::{widgets, Component, HandleInput, HandleRpc, InputResult, Renderable},
+    state::State,
+    tui::{notify, widgets, Component, HandleInput, HandleRpc, InputResult, Renderable},
     utils::{
-        align,
-        color::ColorEscape,
+        self, align,
+        color::{Color, ColorEscape},
         filter::Filter,
         fmt::{self, FormatSize},
     },
 };
 
+mod confirm;
+mod diagnostics;
+mod errors;
 mod login;
+mod peers;
+mod prompt;
+mod server_picker;
 mod torrent_details;
 
+#[cfg(test)]
+mod tests;
+
+pub use self::confirm::Confirm;
+pub use self::diagnostics::Diagnostics;
+pub use self::errors::Errors;
 pub use self::login::Login;
-pub use self::torrent_details::TorrentDetails;
+pub use self::peers::Peers;
+pub use self::prompt::Prompt;
+pub use self::server_picker::ServerPicker;
+pub use self::torrent_details::{DetailsTracker, TorrentDetails};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum Focus {
     Details,
     Filter,
     Torrents,
+    Trackers,
+}
+
+// How the trackers sidebar is ordered, the trackers themselves are always kept sorted by URL
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TrackerSort {
+    Url,
+    // The most torrents first
+    Torrents,
+    // The ones with errors first
+    Errors,
+}
+
+impl TrackerSort {
+    fn next(self) -> TrackerSort {
+        match self {
+            TrackerSort::Url => TrackerSort::Torrents,
+            TrackerSort::Torrents => TrackerSort::Errors,
+            TrackerSort::Errors => TrackerSort::Url,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            TrackerSort::Url => "URL",
+            TrackerSort::Torrents => "number of torrents",
+            TrackerSort::Errors => "errors",
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -52,25 +112,106 @@ pub struct Main {
     focus: Focus,
     filter: Filter,
     filter_disp: bool,
+    // The line number being typed in goto mode
+    goto: Option<widgets::Input>,
+    // The name being searched for in the list, while typed, and the last confirmed one
+    search: (Option<widgets::Input>, String),
     // FIXME: anon names
     // lower bound of torrent selection,  current pos, _
     torrents: (usize, usize, Vec<Torrent>),
-    // status, throttle up/down, ratio, right
-    torrent_widths: (usize, usize, usize, usize, usize),
-    // tracker base, Vec<(tracker id, torrent_id, optional error)>
-    trackers: Vec<(Tracker, Vec<(String, String, Option<String>)>)>,
+    // Torrents matching the filter subscription, but hidden by the client-side filter criteria
+    filtered: Vec<Torrent>,
+    // status, throttle up/down, ratio, eta, right
+    torrent_widths: (usize, usize, usize, usize, usize, usize),
+    trackers: Vec<TrackerRow>,
     trackers_disp: bool,
+    // The indices into trackers in the order they are displayed
+    tracker_order: Vec<usize>,
+    tracker_sort: TrackerSort,
+    // lower bound of tracker selection, current pos in the displayed order; highlighted while the
+    // trackers are focused
+    tracker_sel: (usize, usize),
     details: (usize, Vec<TorrentDetails>),
     server: Server,
+    // The most recent server rates, up and down
+    server_rates: VecDeque<(u64, u64)>,
+    // Whether the free space is below the configured minimum
+    space_low: bool,
+    // The torrent and priority last requested, until the server echoes it
+    priority_pending: Option<(String, u8)>,
     server_version: String,
+    // The serials of the server and tracker filter subscriptions
+    filter_serials: (u64, u64),
+    // Whether the stats of the torrent list are labeled in a line above it
+    torrent_header: bool,
+    // Whether a row with the combined stats of the listed torrents is pinned above them
+    aggregate_row: bool,
+    // Whether every other torrent row is shaded
+    zebra: bool,
+    // Whether the totals of the listed torrents are shown above the server footer
+    totals_disp: bool,
+    // The fraction of the width taken by the trackers, and the lines taken by the details
+    trackers_width: f32,
+    details_height: u16,
+    // Show dates instead of how long ago they were
+    absolute_times: bool,
+    // When torrents were added to the server while connected, until their highlight expires
+    added: HashMap<String, Instant>,
+    // The ids of all torrents the server sent, including those that stopped matching the filter
+    seen: HashSet<String>,
+    // Whether the current input was confirmed already, if its action asks for confirmation
+    confirmed: bool,
+    sink: rpc::WsSink,
 }
 
+// tracker base, Vec<(tracker id, torrent_id, optional error, last report)> of the other torrents'
+// trackers with the same URL
+type TrackerRow = (
+    Tracker,
+    Vec<(String, String, Option<String>, DateTime<Utc>)>,
+);
+
+// How long torrents added while connected are highlighted
+const ADDED_HIGHLIGHT: Duration = Duration::from_secs(5);
+
+// The number of server rate samples kept for the sparklines
+const RATE_SAMPLES: usize = 60;
+
+// The range of torrent priorities synapse accepts
+const MIN_PRIORITY: u8 = 1;
+const MAX_PRIORITY: u8 = 5;
+
+// The width of the trackers sidebar as a fraction of the panel, and the step it's resized by
+const TRACKERS_WIDTH: f32 = 0.2;
+const MIN_TRACKERS_WIDTH: f32 = 0.1;
+const MAX_TRACKERS_WIDTH: f32 = 0.5;
+const TRACKERS_WIDTH_STEP: f32 = 0.05;
+// The columns left to the trackers and torrents, if the terminal is too narrow for the fraction
+const MIN_TRACKERS_COLUMNS: u16 = 8;
+// Below this width the trackers are shown by their tag, and errors by a glyph
+const COMPACT_TRACKERS_COLUMNS: u16 = 20;
+
+// The lines of the details pane, without the divider above it
+const DETAILS_HEIGHT: u16 = 5;
+const MIN_DETAILS_HEIGHT: u16 = 2;
+const MAX_DETAILS_HEIGHT: u16 = 12;
+
 impl Main {
     pub fn new(sink: &rpc::WsSink, height: u16) -> Main {
+        let state = State::load();
+        let filter = Filter::new(
+            sink,
+            if CONFIG.remember_filter {
+                state.filter
+            } else {
+                String::new()
+            },
+        );
+        let filter_serials = (rpc::next_serial(), rpc::next_serial());
         rpc::send(
             sink,
             CMessage::FilterSubscribe {
-                serial: rpc::next_serial(),
+                serial: filter_serials.0,
                 kind: ResourceKind::Server,
                 criteria: Vec::new(),
             },
@@ -78,7 +219,7 @@ impl Main {
         rpc::send(
             sink,
             CMessage::FilterSubscribe {
-                serial: rpc::next_serial(),
+                serial: filter_serials.1,
                 kind: ResourceKind::Tracker,
                 criteria: Vec::new(),
             },
@@ -87,23 +228,370 @@ impl Main {
         Main {
             last_height: height,
             focus: Focus::Torrents,
-            filter: Filter::new(sink),
-            filter_disp: false,
+            filter_disp: !filter.query().is_empty(),
+            filter,
+            goto: None,
+            search: (None, String::new()),
             torrents: (0, 0, Vec::new()),
-            torrent_widths: (0, 0, 0, 0, 0),
+            filtered: Vec::new(),
+            torrent_widths: (0, 0, 0, 0, 0, 0),
             trackers: Vec::new(),
             trackers_disp: false,
+            tracker_order: Vec::new(),
+            tracker_sort: TrackerSort::Url,
+            tracker_sel: (0, 0),
             details: (0, Vec::new()),
             server: Default::default(),
+            server_rates: VecDeque::with_capacity(RATE_SAMPLES),
+            space_low: false,
+            priority_pending: None,
             server_version: "?.?".to_owned(),
+            filter_serials,
+            torrent_header: CONFIG.torrent_header,
+            aggregate_row: CONFIG.aggregate_row,
+            zebra: CONFIG.zebra,
+            totals_disp: false,
+            trackers_width: state.trackers_width.map_or(TRACKERS_WIDTH, |w| {
+                w.clamp(MIN_TRACKERS_WIDTH, MAX_TRACKERS_WIDTH)
+            }),
+            details_height: state.details_height.map_or(DETAILS_HEIGHT, |h| {
+                h.clamp(MIN_DETAILS_HEIGHT, MAX_DETAILS_HEIGHT)
+            }),
+            absolute_times: CONFIG.absolute_times,
+            added: HashMap::new(),
+            seen: HashSet::new(),
+            confirmed: false,
+            sink: Arc::clone(sink),
+        }
+    }
+
+    // Keeps the pane sizes for the next session
+    fn remember_layout(&self) {
+        let mut state = State::load();
+        state.trackers_width = Some(self.trackers_width);
+        state.details_height = Some(self.details_height);
+        state.save();
+    }
+
+    fn remember_filter(&self) {
+        if CONFIG.remember_filter {
+            let mut state = State::load();
+            state.filter = self.filter.query().to_owned();
+            state.save();
+        }
+    }
+
+    fn selected_torrent(&self) -> Option<&Torrent> {
+        match self.focus {
+            Focus::Torrents | Focus::Filter | Focus::Trackers => {
+                self.torrents.2.get(self.torrents.1)
+            }
+            Focus::Details => self.details.1.get(self.details.0).map(|d| d.inner()),
+        }
+    }
+
+    // Prompt for the value stored under key in the torrent's user data, removing it if left empty
+    fn user_data_prompt(
+        &self,
+        t: &Torrent,
+        label: &str,
+        key: &'static str,
+        initial: String,
+    ) -> Prompt {
+        let id = t.id.clone();
+        let mut user_data = t.user_data.clone();
+        let sink = Arc::clone(&self.sink);
+        Prompt::new(label.to_owned(), initial, move |value| {
+            if !user_data.is_object() {
+                user_data = json!({});
+            }
+            let obj = user_data.as_object_mut().unwrap();
+            if value.is_empty() {
+                obj.remove(key);
+            } else {
+                obj.insert(key.to_owned(), json!(value));
+            }
+            rpc::send(
+                &sink,
+                CMessage::UpdateResource {
+                    serial: rpc::next_serial(),
+                    resource: CResourceUpdate {
+                        id: id.clone(),
+                        user_data: Some(user_data.clone()),
+                        ..Default::default()
+                    },
+                },
+            );
+            Ok(())
+        })
+    }
+
+    // The details tabs show copies of the torrents, which are updated from the list by id, since
+    // only the list receives all updates, e.g. when a torrent is sent again after refiltering
+    fn refresh_details(&mut self) {
+        for dt in &mut self.details.1 {
+            if let Some(t) = self
+                .torrents
+                .2
+                .iter()
+                .chain(self.filtered.iter())
+                .find(|t| t.id == dt.inner().id)
+            {
+                *dt.inner_mut() = t.clone();
+            }
+        }
+        let trackers: Vec<_> = self
+            .details
+            .1
+            .iter()
+            .map(|dt| self.torrent_trackers(dt.inner()))
+            .collect();
+        for (dt, trackers) in self.details.1.iter_mut().zip(trackers) {
+            dt.set_trackers(trackers);
         }
     }
 
+    // The trackers of the torrent in the order it lists them, including those whose resource did
+    // not arrive yet
+    fn torrent_trackers(&self, t: &Torrent) -> Vec<DetailsTracker> {
+        let mut trackers: Vec<_> = self
+            .trackers
+            .iter()
+            .filter_map(|(base, others)| {
+                let (error, last_report) = if base.torrent_id == t.id {
+                    (&base.error, base.last_report)
+                } else {
+                    let other = others.iter().find(|o| o.1 == t.id)?;
+                    (&other.2, other.3)
+                };
+                Some(DetailsTracker {
+                    host: tracker_host(base).to_owned(),
+                    status: Some((base.url.clone(), last_report, error.clone())),
+                })
+            })
+            .collect();
+        for host in &t.tracker_urls {
+            if !trackers.iter().any(|tr| tr.host == *host) {
+                trackers.push(DetailsTracker {
+                    host: host.clone(),
+                    status: None,
+                });
+            }
+        }
+        trackers.sort_by_key(|tr| {
+            t.tracker_urls
+                .iter()
+                .position(|h| *h == tr.host)
+                .unwrap_or(t.tracker_urls.len())
+        });
+        trackers
+    }
+
+    // The action of the key, if it is handled in the current focus and asks for confirmation
+    fn gated_action(&self, k: Key) -> Option<Action> {
+        CONFIG.confirm.iter().cloned().find(|&a| {
+            CONFIG.keys.is(a, k)
+                && match a {
+                    // Asked by the view, before the input reaches any panel
+                    Action::Quit => false,
+                    Action::Reannounce => self.focus == Focus::Trackers,
+                    _ => self.focus == Focus::Torrents || self.focus == Focus::Details,
+                }
+        })
+    }
+
+    // What is asked before the action is run, none if there is nothing to run it on
+    fn confirm_question(&self, action: Action) -> Option<String> {
+        if action == Action::Reannounce {
+            return self
+                .selected_tracker()
+                .map(|(base, _)| format!("Reannounce to {}?", tracker_host(base)));
+        }
+        let t = self.selected_torrent()?;
+        let name = fmt::torrent_name(t);
+        Some(match action {
+            Action::Validate => format!("Validate {}?", name),
+            Action::Sequential if t.strategy == Strategy::Sequential => {
+                format!("Download {} rarest first?", name)
+            }
+            Action::Sequential => format!("Download {} sequentially?", name),
+            Action::PriorityDown => format!("Lower the priority of {}?", name),
+            Action::PriorityUp => format!("Raise the priority of {}?", name),
+            Action::OpenPath => format!("Open {}?", t.path),
+            _ => return None,
+        })
+    }
+
+    // The row of the highlighted tracker
+    fn selected_tracker(&self) -> Option<&TrackerRow> {
+        self.tracker_order
+            .get(self.tracker_sel.1)
+            .map(|&i| &self.trackers[i])
+    }
+
+    // Recomputes the displayed order of the trackers, and keeps the highlight on the tracker with
+    // the URL sel, if it's still listed
+    fn sort_trackers(&mut self, sel: Option<Url>) {
+        let mut order: Vec<_> = (0..self.trackers.len()).collect();
+        // The sort is stable, so ties stay ordered by URL
+        match self.tracker_sort {
+            TrackerSort::Url => {}
+            TrackerSort::Torrents => {
+                order.sort_by_key(|&i| cmp::Reverse(self.trackers[i].1.len()));
+            }
+            TrackerSort::Errors => order.sort_by_key(|&i| {
+                let (ref base, ref others) = self.trackers[i];
+                base.error.is_none() && others.iter().all(|o| o.2.is_none())
+            }),
+        }
+        self.tracker_order = order;
+
+        let trackers = &self.trackers;
+        if let Some(pos) = sel.and_then(|url| {
+            self.tracker_order
+                .iter()
+                .position(|&i| trackers[i].0.url == url)
+        }) {
+            self.tracker_sel.1 = pos;
+        }
+        self.tracker_sel.1 = cmp::min(self.tracker_sel.1, self.trackers.len().saturating_sub(1));
+        self.tracker_sel.0 = cmp::min(self.tracker_sel.0, self.tracker_sel.1);
+    }
+
+    // Whether a tracker of the torrent reported an error
+    fn tracker_error(&self, t: &Torrent) -> bool {
+        self.trackers
+            .iter()
+            .filter(|tra| t.tracker_urls.iter().any(|tu| *tu == tracker_host(&tra.0)))
+            .any(|(ref base, ref others)| {
+                base.error.is_some()
+                    || others
+                        .iter()
+                        .any(|(_, id, e, _)| t.id == *id && e.is_some())
+            })
+    }
+
+    // The next, or previous, torrent after the selected one that satisfies pred, wrapping around
+    fn find_torrent<F: Fn(&Torrent) -> bool>(&self, next: bool, pred: F) -> Option<usize> {
+        let len = self.torrents.2.len();
+        (1..=len)
+            .map(|off| {
+                if next {
+                    (self.torrents.1 + off) % len
+                } else {
+                    (self.torrents.1 + len - off) % len
+                }
+            })
+            .find(|&idx| pred(&self.torrents.2[idx]))
+    }
+
+    fn select_error(&mut self, next: bool, list_height: usize) {
+        if let Some(idx) = self.find_torrent(next, |t| t.error.is_some() || self.tracker_error(t)) {
+            self.select_torrent(idx, list_height);
+        }
+    }
+
+    fn select_match(&mut self, next: bool, list_height: usize) {
+        if let Some(idx) = self.find_torrent(next, |t| self.search_matches(t)) {
+            self.select_torrent(idx, list_height);
+        }
+    }
+
+    // Whether the name contains the search being typed, or else the confirmed one
+    fn search_matches(&self, t: &Torrent) -> bool {
+        let query = match self.search.0 {
+            Some(ref search) => search.inner(),
+            None => &self.search.1,
+        };
+        !query.is_empty()
+            && fmt::torrent_name(t)
+                .to_lowercase()
+                .contains(&query.to_lowercase())
+    }
+
+    // Select the torrent at idx, and scroll the list just enough for it to be visible
+    fn select_torrent(&mut self, idx: usize, list_height: usize) {
+        self.torrents.1 = idx;
+        if idx < self.torrents.0 {
+            self.torrents.0 = idx;
+        } else if idx >= self.torrents.0 + list_height {
+            self.torrents.0 = (idx + 1).saturating_sub(list_height);
+        }
+        self.recompute_torrent_bounds(list_height as u16);
+    }
+
+    // Move the torrent to its sorted position after its name changed, keeping the selection on the
+    // same torrent
+    fn resort(&mut self, id: &str) {
+        let pos = match self.torrents.2.iter().position(|t| t.id == id) {
+            Some(pos) => pos,
+            None => return,
+        };
+        let selected = self.torrents.2.get(self.torrents.1).map(|t| t.id.clone());
+        let t = self.torrents.2.remove(pos);
+        let idx = sorted_pos(&self.torrents.2, &t);
+        self.torrents.2.insert(idx, t);
+
+        let sel = selected.and_then(|sel| self.torrents.2.iter().position(|t| t.id == sel));
+        if let Some(sel) = sel {
+            let sub = if self.details.1.is_empty() {
+                4
+            } else {
+                4 + self.details_height
+            };
+            let list_height = self.last_height.saturating_sub(sub + self.extra_lines());
+            self.select_torrent(sel, list_height as _);
+        }
+    }
+
+    // Notify once when the free space drops below the configured minimum
+    fn check_space(&mut self) {
+        let low = CONFIG
+            .min_free_space_mib
+            .is_some_and(|min| self.server.free_space < min * 1024 * 1024);
+        if low && !self.space_low {
+            notify::push(format!(
+                "Low free space on the server: {}",
+                self.server.free_space.fmt_size()
+            ));
+        }
+        self.space_low = low;
+    }
+
+    // Move torrents between the list and the hidden torrents according to the client-side filter
+    fn refilter(&mut self) {
+        let mut i = 0;
+        while i < self.torrents.2.len() {
+            if self.filter.matches(&self.torrents.2[i]) {
+                i += 1;
+            } else {
+                let t = self.torrents.2.remove(i);
+                self.filtered.push(t);
+            }
+        }
+        i = 0;
+        while i < self.filtered.len() {
+            if self.filter.matches(&self.filtered[i]) {
+                let t = self.filtered.swap_remove(i);
+                let idx = sorted_pos(&self.torrents.2, &t);
+                self.torrents.2.insert(idx, t);
+            } else {
+                i += 1;
+            }
+        }
+
+        self.torrents.1 = cmp::min(self.torrents.1, self.torrents.2.len().saturating_sub(1));
+        self.torrents.0 = cmp::min(self.torrents.0, self.torrents.1);
+        let h = self.last_height;
+        self.recompute_torrent_bounds(h);
+    }
+
     fn recompute_torrent_bounds(&mut self, height: u16) {
         self.torrent_widths.0 = 0;
         self.torrent_widths.1 = 0;
         self.torrent_widths.2 = 0;
         self.torrent_widths.3 = 0;
+        self.torrent_widths.4 = 0;
         for t in self
             .torrents
             .2
@@ -112,16 +600,18 @@ impl Main {
             .take(height as _)
         {
             self.torrent_widths.0 = cmp::max(self.torrent_widths.0, t.status.as_str().len());
+            self.torrent_widths.4 =
+                cmp::max(self.torrent_widths.4, utils::count(&fmt::torrent_eta(t)));
             self.torrent_widths.1 = cmp::max(
                 self.torrent_widths.1,
                 t.throttle_up
-                    .map(|t| if t == -1 { 1 } else { 10 })
+                    .map(|t| if t == -1 { 1 } else { fmt::rate_align_width() })
                     .unwrap_or(1),
             );
             self.torrent_widths.2 = cmp::max(
                 self.torrent_widths.2,
                 t.throttle_down
-                    .map(|t| if t == -1 { 1 } else { 10 })
+                    .map(|t| if t == -1 { 1 } else { fmt::rate_align_width() })
                     .unwrap_or(1),
             );
             self.torrent_widths.3 = cmp::max(
@@ -141,37 +631,450 @@ impl Main {
             );
         }
 
-        self.torrent_widths.4 = 62
-            + self.torrent_widths.0
-            + self.torrent_widths.1
-            + self.torrent_widths.2
-            + self.torrent_widths.3;
+        self.torrent_widths.5 = CONFIG
+            .torrent_columns
+            .iter()
+            .enumerate()
+            .map(|(i, &col)| {
+                let sep = if i == 0 { 0 } else { column_sep(col).len() };
+                sep + self.column_width(col)
+            })
+            .sum();
+    }
+
+    // The width of a stats column, without its separator
+    fn column_width(&self, col: TorrentColumn) -> usize {
+        match col {
+            TorrentColumn::Progress => 4,
+            TorrentColumn::Eta => self.torrent_widths.4,
+            TorrentColumn::Status => self.torrent_widths.0,
+            // Two aligned rates and their throttles, and the brackets and arrows
+            TorrentColumn::Rates => {
+                2 * fmt::rate_align_width() + self.torrent_widths.1 + self.torrent_widths.2 + 7
+            }
+            TorrentColumn::Ratio => self.torrent_widths.3,
+            // Two aligned sizes, and the arrows and separator
+            TorrentColumn::Lifetime => 2 * fmt::size_align_width() + 4,
+        }
+    }
+
+    // The stats of a torrent in the enabled columns
+    fn torrent_stats(&self, t: &Torrent) -> String {
+        let throttle = |th: Option<i64>| {
+            th.map(|th| {
+                if th == -1 {
+                    "∞".into()
+                } else {
+                    th.fmt_rate_align()
+                }
+            })
+            .unwrap_or_else(|| "*".into())
+        };
+        join_columns(|col| match col {
+            TorrentColumn::Progress => format!("{: >3}%", (t.progress * 100.).round()),
+            TorrentColumn::Eta => format!(
+                "{: >w_eta$}",
+                fmt::torrent_eta(t),
+                w_eta = self.torrent_widths.4
+            ),
+            TorrentColumn::Status => format!(
+                "{: ^w_status$}",
+                t.status.as_str(),
+                w_status = self.torrent_widths.0
+            ),
+            TorrentColumn::Rates => format!(
+                "{}[{: ^w_tu$}]↑ {}[{: ^w_td$}]↓",
+                t.rate_up.fmt_rate_align(),
+                throttle(t.throttle_up),
+                t.rate_down.fmt_rate_align(),
+                throttle(t.throttle_down),
+                w_tu = self.torrent_widths.1,
+                w_td = self.torrent_widths.2,
+            ),
+            TorrentColumn::Ratio => format!(
+                "{: >w_rat$}",
+                fmt::fmt_ratio(t.transferred_up, t.transferred_down),
+                w_rat = self.torrent_widths.3
+            ),
+            TorrentColumn::Lifetime => format!(
+                "{}↑  {}↓",
+                t.transferred_up.fmt_size_align(),
+                t.transferred_down.fmt_size_align()
+            ),
+        })
+    }
+
+    // The stats of all listed torrents combined, aligned with the columns of the rows
+    fn aggregate_stats(&self) -> String {
+        let (size, done, up, down, tr_up, tr_down) =
+            self.torrents.2.iter().fold((0, 0., 0, 0, 0, 0), |acc, t| {
+                let size = t.size.unwrap_or(0);
+                (
+                    acc.0 + size,
+                    acc.1 + size as f64 * f64::from(t.progress),
+                    acc.2 + t.rate_up,
+                    acc.3 + t.rate_down,
+                    acc.4 + t.transferred_up,
+                    acc.5 + t.transferred_down,
+                )
+            });
+        // Weighted by size, so that small torrents don't skew it
+        let progress = if size == 0 { 0. } else { done / size as f64 };
+        join_columns(|col| match col {
+            TorrentColumn::Progress => format!("{: >3}%", (progress * 100.).round()),
+            TorrentColumn::Eta | TorrentColumn::Status => " ".repeat(self.column_width(col)),
+            // Blanks in place of the throttles and their brackets
+            TorrentColumn::Rates => format!(
+                "{} {: ^w_tu$} ↑ {} {: ^w_td$} ↓",
+                up.fmt_rate_align(),
+                "",
+                down.fmt_rate_align(),
+                "",
+                w_tu = self.torrent_widths.1,
+                w_td = self.torrent_widths.2,
+            ),
+            TorrentColumn::Ratio => format!(
+                "{: >w_rat$}",
+                fmt::fmt_ratio(tr_up, tr_down),
+                w_rat = self.torrent_widths.3
+            ),
+            TorrentColumn::Lifetime => {
+                format!("{}↑  {}↓", tr_up.fmt_size_align(), tr_down.fmt_size_align())
+            }
+        })
+    }
+
+    // The lines taken from the torrent list by the header, aggregate row, and totals
+    fn extra_lines(&self) -> u16 {
+        self.torrent_header as u16 + self.aggregate_row as u16 + self.totals_disp as u16
+    }
+
+    // The number of listed torrents seeding, leeching, and with an error
+    fn status_counts(&self) -> String {
+        let (seed, leech, err) = self
+            .torrents
+            .2
+            .iter()
+            .fold((0, 0, 0), |(seed, leech, err), t| match t.status {
+                Status::Seeding => (seed + 1, leech, err),
+                Status::Leeching => (seed, leech + 1, err),
+                Status::Error => (seed, leech, err + 1),
+                _ => (seed, leech, err),
+            });
+        let err = if err > 0 {
+            format!(
+                "{}{} err{}",
+                ColorEscape::error(),
+                err,
+                ColorEscape::reset()
+            )
+        } else {
+            "0 err".to_owned()
+        };
+        format!("▸ {} seed  {} leech  {}", seed, leech, err)
+    }
+
+    // The number, total size, and combined rates of the listed torrents
+    fn totals(&self) -> String {
+        let (size, up, down) = self.torrents.2.iter().fold((0, 0, 0), |acc, t| {
+            (
+                acc.0 + t.size.unwrap_or(0),
+                acc.1 + t.rate_up,
+                acc.2 + t.rate_down,
+            )
+        });
+        format!(
+            "{} torrents: {} at {}↑ {}↓",
+            self.torrents.2.len(),
+            size.fmt_size(),
+            up.fmt_rate(),
+            down.fmt_rate()
+        )
+    }
+
+    // Labels the stats columns of the torrent list, with the same widths as the rows
+    fn torrent_header(&self) -> String {
+        // Labels are abbreviated rather than misaligning the columns
+        let fit = |label: &'static str, w: usize| {
+            if label.len() <= w {
+                label
+            } else {
+                &label[..cmp::min(1, w)]
+            }
+        };
+        join_columns(|col| match col {
+            TorrentColumn::Progress => format!("{: >4}", "%"),
+            TorrentColumn::Eta => format!(
+                "{: >w_eta$}",
+                fit("ETA", self.torrent_widths.4),
+                w_eta = self.torrent_widths.4
+            ),
+            TorrentColumn::Status => format!(
+                "{: ^w_status$}",
+                fit("Status", self.torrent_widths.0),
+                w_status = self.torrent_widths.0
+            ),
+            TorrentColumn::Rates => format!(
+                "{: ^w_up$} {: ^w_down$}",
+                "↑",
+                "↓",
+                w_up = fmt::rate_align_width() + self.torrent_widths.1 + 3,
+                w_down = fmt::rate_align_width() + self.torrent_widths.2 + 3,
+            ),
+            TorrentColumn::Ratio => format!(
+                "{: >w_rat$}",
+                fit("Ratio", self.torrent_widths.3),
+                w_rat = self.torrent_widths.3
+            ),
+            TorrentColumn::Lifetime => {
+                let w_size = fmt::size_align_width() + 1;
+                format!(
+                    "{: >w_size$}  {: >w_size$}",
+                    fit("Up", w_size),
+                    fit("Down", w_size),
+                    w_size = w_size
+                )
+            }
+        })
+    }
+}
+
+// The spacing before a stats column, unless it is the first one
+fn column_sep(col: TorrentColumn) -> &'static str {
+    match col {
+        TorrentColumn::Ratio => "   ",
+        TorrentColumn::Lifetime => "  ",
+        _ => " ",
     }
 }
 
-impl Component for Main {}
+// Concatenates the enabled stats columns with their separators
+fn join_columns<F: FnMut(TorrentColumn) -> String>(mut cell: F) -> String {
+    let mut s = String::new();
+    for (i, &col) in CONFIG.torrent_columns.iter().enumerate() {
+        if i != 0 {
+            s.push_str(column_sep(col));
+        }
+        s.push_str(&cell(col));
+    }
+    s
+}
+
+// The position to insert the torrent at, keeping the list sorted by name
+fn sorted_pos(torrents: &[Torrent], t: &Torrent) -> usize {
+    torrents
+        .binary_search_by(|probe| {
+            natord::compare_ignore_case(fmt::torrent_name(probe), fmt::torrent_name(t))
+        })
+        .unwrap_or_else(|e| e)
+}
+
+// Not every tracker URL has a host, e.g. malformed announce URLs, so fall back to the whole URL
+fn tracker_host(t: &Tracker) -> &str {
+    t.url.host_str().unwrap_or_else(|| t.url.as_str())
+}
+
+// The first label of the tracker's domain, e.g. `tracker` of `tracker.example.org`. Addresses are
+// kept whole, since their first part does not tell them apart
+fn tracker_tag(t: &Tracker) -> &str {
+    t.url
+        .domain()
+        .and_then(|d| d.split('.').next())
+        .unwrap_or_else(|| tracker_host(t))
+}
+
+impl Component for Main {
+    fn transferring(&self) -> bool {
+        // The server rate includes torrents hidden by the filter
+        self.server.rate_up > 0
+            || self.server.rate_down > 0
+            || self
+                .torrents
+                .2
+                .iter()
+                .any(|t| t.rate_up > 0 || t.rate_down > 0)
+    }
+
+    fn expire(&mut self) -> bool {
+        let len = self.added.len();
+        self.added.retain(|_, at| at.elapsed() < ADDED_HIGHLIGHT);
+        self.added.len() != len
+    }
+
+    fn typing(&self) -> bool {
+        self.goto.is_some() || self.search.0.is_some() || self.focus == Focus::Filter
+    }
+
+    fn input_confirmed(&mut self, action: Action, width: u16, height: u16) -> InputResult {
+        self.confirmed = true;
+        let res = self.input(CONFIG.keys.get(action), width, height);
+        self.confirmed = false;
+        res
+    }
+
+    fn teardown(&mut self) {
+        self.filter.unsubscribe();
+        for &filter_serial in &[self.filter_serials.0, self.filter_serials.1] {
+            rpc::send(
+                &self.sink,
+                CMessage::FilterUnsubscribe {
+                    serial: rpc::next_serial(),
+                    filter_serial,
+                },
+            );
+        }
+
+        // The resources themselves were subscribed to when they became extant
+        let mut ids: Vec<_> = self
+            .torrents
+            .2
+            .iter()
+            .chain(&self.filtered)
+            .map(|t| t.id.clone())
+            .collect();
+        for (base, others) in &self.trackers {
+            ids.push(base.id.clone());
+            ids.extend(others.iter().map(|o| o.0.clone()));
+        }
+        if !self.server.id.is_empty() {
+            ids.push(self.server.id.clone());
+        }
+        if !ids.is_empty() {
+            rpc::send(
+                &self.sink,
+                CMessage::Unsubscribe {
+                    serial: rpc::next_serial(),
+                    ids,
+                },
+            );
+        }
+    }
+}
 
 impl HandleInput for Main {
     fn input(&mut self, k: Key, width: u16, height: u16) -> InputResult {
         // - 2 because of the server footer
-        let torr_height = height.saturating_sub(2) as usize;
+        let torr_height = height.saturating_sub(2 + self.extra_lines()) as usize;
         let torr_list_height = if self.details.1.is_empty() {
             (torr_height as u16).saturating_sub(2)
         } else {
-            (torr_height as u16).saturating_sub(2 + 5)
+            (torr_height as u16).saturating_sub(2 + self.details_height)
         };
 
+        let keys = &CONFIG.keys;
+        let typing = self.typing();
+
+        if let Some(mut goto) = self.goto.take() {
+            match k {
+                Key::Esc => {}
+                Key::Char('\n') => {
+                    if let (Ok(n), false) =
+                        (goto.inner().parse::<usize>(), self.torrents.2.is_empty())
+                    {
+                        let idx = n.clamp(1, self.torrents.2.len()) - 1;
+                        self.select_torrent(idx, torr_list_height as _);
+                    }
+                }
+                Key::Backspace => {
+                    goto.backspace();
+                    self.goto = Some(goto);
+                }
+                Key::Delete => {
+                    goto.delete();
+                    self.goto = Some(goto);
+                }
+                Key::Left => {
+                    goto.cursor_left();
+                    self.goto = Some(goto);
+                }
+                Key::Right => {
+                    goto.cursor_right();
+                    self.goto = Some(goto);
+                }
+                Key::Char(c) if c.is_ascii_digit() => {
+                    goto.push(c);
+                    self.goto = Some(goto);
+                }
+                _ => {
+                    self.goto = Some(goto);
+                }
+            }
+            return InputResult::Rerender;
+        }
+
+        if let Some(mut search) = self.search.0.take() {
+            match k {
+                Key::Esc => {}
+                Key::Char('\n') => {
+                    self.search.1 = search.inner().to_owned();
+                    let first = self.torrents.2.iter().position(|t| self.search_matches(t));
+                    if let Some(idx) = first {
+                        self.select_torrent(idx, torr_list_height as _);
+                    }
+                }
+                Key::Backspace => {
+                    search.backspace();
+                    self.search.0 = Some(search);
+                }
+                Key::Delete => {
+                    search.delete();
+                    self.search.0 = Some(search);
+                }
+                Key::Left => {
+                    search.cursor_left();
+                    self.search.0 = Some(search);
+                }
+                Key::Right => {
+                    search.cursor_right();
+                    self.search.0 = Some(search);
+                }
+                Key::Char(c) => {
+                    search.push(c);
+                    self.search.0 = Some(search);
+                }
+                _ => {
+                    self.search.0 = Some(search);
+                }
+            }
+            return InputResult::Rerender;
+        }
+
+        if let (Some(action), false) = (self.gated_action(k), self.confirmed) {
+            return match self.confirm_question(action) {
+                Some(question) => InputResult::ReplaceWith(Box::new(Confirm::new(
+                    question,
+                    action,
+                    Box::new(self.clone()),
+                ))),
+                None => InputResult::Key(k),
+            };
+        }
+
         match (k, self.focus) {
             // Special keys
-            (Key::Ctrl('f'), Focus::Filter) => {
+            (k, _) if keys.is_unless_typing(Action::Diagnostics, k, typing) => {
+                return InputResult::ReplaceWith(Box::new(widgets::OwnedOverlay::new(
+                    Diagnostics,
+                    Box::new(self.clone()),
+                    (
+                        width.saturating_sub(4),
+                        cmp::min(height.saturating_sub(4), diagnostics::HEIGHT),
+                    ),
+                    None,
+                    "Diagnostics".to_owned(),
+                )) as Box<dyn Component>);
+            }
+            (k, Focus::Filter) if keys.is_unless_typing(Action::ClearFilter, k, typing) => {
                 self.focus = Focus::Torrents;
                 self.filter.reset();
                 self.filter_disp = false;
-                self.recompute_torrent_bounds(torr_list_height);
+                self.refilter();
+                self.remember_filter();
             }
 
             (Key::Esc, Focus::Filter) => {
                 self.focus = Focus::Torrents;
+                self.remember_filter();
             }
 
             // Movement Keys
@@ -227,8 +1130,24 @@ impl HandleInput for Main {
                 self.recompute_torrent_bounds(torr_list_height);
             }
 
-            (Key::Up, Focus::Torrents) | (Key::Char('k'), Focus::Torrents)
-                if self.torrents.1 > 0 =>
+            (k, Focus::Torrents) if keys.is(Action::HalfPageUp, k) => {
+                let half = cmp::max(torr_height / 2, 1);
+                self.torrents.0 = self.torrents.0.saturating_sub(half);
+                self.torrents.1 = self.torrents.1.saturating_sub(half);
+                self.recompute_torrent_bounds(torr_list_height);
+            }
+            (k, Focus::Torrents)
+                if keys.is(Action::HalfPageDown, k) && !self.torrents.2.is_empty() =>
+            {
+                let half = cmp::max(torr_height / 2, 1);
+                let l = self.torrents.2.len();
+                self.torrents.0 = cmp::min(self.torrents.0 + half, l.saturating_sub(torr_height));
+                self.torrents.1 = cmp::min(self.torrents.1 + half, l - 1);
+                self.recompute_torrent_bounds(torr_list_height);
+            }
+
+            (k, Focus::Torrents)
+                if (k == Key::Up || keys.is(Action::Up, k)) && self.torrents.1 > 0 =>
             {
                 if self.torrents.0 == self.torrents.1 {
                     self.torrents.0 -= 1;
@@ -237,8 +1156,9 @@ impl HandleInput for Main {
                 self.recompute_torrent_bounds(torr_list_height);
             }
 
-            (Key::Down, Focus::Torrents) | (Key::Char('j'), Focus::Torrents)
-                if self.torrents.1 + 1 < self.torrents.2.len() =>
+            (k, Focus::Torrents)
+                if (k == Key::Down || keys.is(Action::Down, k))
+                    && self.torrents.1 + 1 < self.torrents.2.len() =>
             {
                 if self.torrents.0 + torr_height.saturating_sub(1) == self.torrents.1 {
                     self.torrents.0 += 1;
@@ -247,21 +1167,49 @@ impl HandleInput for Main {
                 self.recompute_torrent_bounds(torr_list_height);
             }
 
-            (Key::Left, Focus::Details) | (Key::Char('h'), Focus::Details)
-                if self.details.0 > 0 =>
+            // While searching, the matches are cycled through instead of the errors
+            (k, Focus::Torrents) if keys.is(Action::NextError, k) && !self.search.1.is_empty() => {
+                self.select_match(true, torr_list_height as _);
+            }
+            (k, Focus::Torrents) if keys.is(Action::PrevError, k) && !self.search.1.is_empty() => {
+                self.select_match(false, torr_list_height as _);
+            }
+            (Key::Esc, Focus::Torrents) if !self.search.1.is_empty() => {
+                self.search.1.clear();
+            }
+            (k, Focus::Torrents) if keys.is(Action::NextError, k) => {
+                self.select_error(true, torr_list_height as _);
+            }
+            (k, Focus::Torrents) if keys.is(Action::PrevError, k) => {
+                self.select_error(false, torr_list_height as _);
+            }
+
+            (k, Focus::Details)
+                if (k == Key::Left || keys.is(Action::Left, k)) && self.details.0 > 0 =>
             {
                 self.details.0 -= 1;
             }
 
-            (Key::Right, Focus::Details) | (Key::Char('l'), Focus::Details)
-                if self.details.0 + 1 != self.details.1.len() =>
+            (k, Focus::Details)
+                if (k == Key::Right || keys.is(Action::Right, k))
+                    && self.details.0 + 1 != self.details.1.len() =>
             {
                 self.details.0 += 1;
             }
 
-            // Key::Char
-            //(Key::Char('\n'), Focus::Torrents) => unimplemented!("OPEN DIR"),
-            (Key::Char('d'), Focus::Torrents) if !self.torrents.2.is_empty() => {
+            // Move the current details tab, it stays selected
+            (k, Focus::Details) if keys.is(Action::FocusLeft, k) && self.details.0 > 0 => {
+                self.details.1.swap(self.details.0, self.details.0 - 1);
+                self.details.0 -= 1;
+            }
+            (k, Focus::Details)
+                if keys.is(Action::FocusRight, k) && self.details.0 + 1 != self.details.1.len() =>
+            {
+                self.details.1.swap(self.details.0, self.details.0 + 1);
+                self.details.0 += 1;
+            }
+
+            (k, Focus::Torrents) if keys.is(Action::Details, k) && !self.torrents.2.is_empty() => {
                 if let Some(pos) = self
                     .details
                     .1
@@ -272,14 +1220,16 @@ impl HandleInput for Main {
                 } else {
                     self.details.1.push(TorrentDetails::new(
                         self.torrents.2[self.torrents.1].clone(),
+                        self.absolute_times,
                     ));
                     self.details.0 = self.details.1.len() - 1;
+                    self.refresh_details();
                 }
                 self.focus = Focus::Details;
-                self.recompute_torrent_bounds(torr_list_height.saturating_sub(5));
+                self.recompute_torrent_bounds(torr_list_height.saturating_sub(self.details_height));
             }
 
-            (Key::Char('e'), Focus::Torrents) | (Key::Char('e'), Focus::Details) => {
+            (k, Focus::Torrents) | (k, Focus::Details) if keys.is(Action::Errors, k) => {
                 return if self.focus == Focus::Torrents {
                     self.torrents.2.get(self.torrents.1)
                 } else {
@@ -292,102 +1242,409 @@ impl HandleInput for Main {
                         tree.push(e.clone());
                         len = cmp::max(len, e.len() as u16);
                     };
-                    for &(ref base, ref others) in self.trackers.iter().filter(|tra| {
-                        t.tracker_urls
-                            .iter()
-                            .any(|tu| *tu == tra.0.url.host_str().unwrap())
-                    }) {
-                        let mut other_errs = others
-                            .iter()
-                            .filter(|&&(_, ref id, ref err)| t.id == *id && err.is_some())
-                            .map(|&(_, _, ref err)| err.as_ref().unwrap().clone())
-                            .peekable();
-                        if base.error.is_some() && base.torrent_id == t.id {
-                            let s = format!(
-                                "{}: {}",
-                                base.url.host_str().unwrap(),
-                                base.error.as_ref().unwrap().clone(),
-                            );
+                    for &(ref base, ref others) in self
+                        .trackers
+                        .iter()
+                        .filter(|tra| t.tracker_urls.iter().any(|tu| *tu == tracker_host(&tra.0)))
+                    {
+                        // The row groups the trackers of all torrents with this URL, each announces
+                        // on its own
+                        let own = Some((&base.torrent_id, &base.error, base.last_report))
+                            .into_iter()
+                            .chain(others.iter().map(|o| (&o.1, &o.2, o.3)))
+                            .filter(|o| *o.0 == t.id);
+                        for (_, e, last_report) in own {
+                            let e = match e {
+                                Some(e) => e,
+                                None => continue,
+                            };
+                            let s = format!("{}: {}", tracker_host(base), e);
                             len = cmp::max(len, s.len() as u16);
                             tree.push(s);
-                        } else if other_errs.peek().is_some() {
-                            let s = format!(
-                                "{}: {}",
-                                base.url.host_str().unwrap(),
-                                other_errs.next().unwrap()
-                            );
+                            let s = format!(" last announce: {}", fmt::date_ago(last_report));
                             len = cmp::max(len, s.len() as u16);
                             tree.push(s);
                         }
-                        for e in other_errs {
-                            let s = format!(" {}", e);
-                            len = cmp::max(len, s.len() as u16);
-                            tree.push(s);
+                    }
+                    if tree.is_empty() {
+                        return None;
+                    }
+
+                    // Leave room for the box and the footer, the rest is scrolled
+                    let tlen = cmp::max(cmp::min(tree.len() as u16, height.saturating_sub(4)), 1);
+                    // FIXME: Cloning self here is pretty hacky
+                    Some(InputResult::ReplaceWith(
+                        Box::new(widgets::OwnedOverlay::new(
+                            Errors::new(tree),
+                            Box::new(self.clone()),
+                            (len, tlen),
+                            Some(ColorEscape::error()),
+                            "Errors".to_owned(),
+                        )) as Box<Component>,
+                    ))
+                })
+                .unwrap_or(InputResult::Key(k));
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) if keys.is(Action::Peers, k) => {
+                return self
+                    .selected_torrent()
+                    .map(|t| {
+                        InputResult::ReplaceWith(Box::new(widgets::OwnedOverlay::new(
+                            Peers::new(&self.sink, t.id.clone()),
+                            Box::new(self.clone()),
+                            (cmp::max(width / 4 * 3, 1), cmp::max(height / 2, 1)),
+                            None,
+                            "Peers".to_owned(),
+                        )) as Box<dyn Component>)
+                    })
+                    .unwrap_or(InputResult::Key(k));
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) if keys.is(Action::Validate, k) => {
+                // Magnets have no data yet that could be validated
+                let id = match self.selected_torrent() {
+                    Some(Torrent {
+                        status: Status::Magnet,
+                        ..
+                    })
+                    | None => return InputResult::Key(k),
+                    Some(t) => {
+                        notify::push(format!("Validating {}", fmt::torrent_name(t)));
+                        t.id.clone()
+                    }
+                };
+                rpc::send(
+                    &self.sink,
+                    CMessage::ValidateResources {
+                        serial: rpc::next_serial(),
+                        ids: vec![id],
+                    },
+                );
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) if keys.is(Action::OpenPath, k) => {
+                // The path is the server's, so this is only useful if it runs on this machine
+                let path = match self.selected_torrent() {
+                    Some(t) => t.path.clone(),
+                    None => return InputResult::Key(k),
+                };
+                match utils::open_path(&path) {
+                    Ok(()) => notify::push(format!("Opening {}", path)),
+                    Err(e) => {
+                        return InputResult::ReplaceWith(Box::new(widgets::error_overlay(
+                            "Open path".to_owned(),
+                            e,
+                            Box::new(self.clone()),
+                            width.saturating_sub(4),
+                        )));
+                    }
+                }
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) if keys.is(Action::Sequential, k) => {
+                // The picker is only changed locally once the server echoes the update
+                let (id, strategy) = match self.selected_torrent() {
+                    Some(t) => (
+                        t.id.clone(),
+                        match t.strategy {
+                            Strategy::Rarest => Strategy::Sequential,
+                            Strategy::Sequential => Strategy::Rarest,
+                        },
+                    ),
+                    None => return InputResult::Key(k),
+                };
+                rpc::send(
+                    &self.sink,
+                    CMessage::UpdateResource {
+                        serial: rpc::next_serial(),
+                        resource: CResourceUpdate {
+                            id,
+                            strategy: Some(strategy),
+                            ..Default::default()
+                        },
+                    },
+                );
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details)
+                if keys.is(Action::PriorityDown, k) || keys.is(Action::PriorityUp, k) =>
+            {
+                let (id, priority) = match self.selected_torrent() {
+                    Some(t) => {
+                        // Repeated presses build on the requested priority, not the stale one
+                        let current = match self.priority_pending {
+                            Some((ref id, p)) if *id == t.id => p,
+                            _ => t.priority,
+                        };
+                        let priority = if keys.is(Action::PriorityUp, k) {
+                            cmp::min(current.saturating_add(1), MAX_PRIORITY)
+                        } else {
+                            cmp::max(current.saturating_sub(1), MIN_PRIORITY)
+                        };
+                        // Already at the bound, so there's nothing to request
+                        if priority == current {
+                            return InputResult::Rerender;
                         }
+                        (t.id.clone(), priority)
+                    }
+                    None => return InputResult::Key(k),
+                };
+                rpc::send(
+                    &self.sink,
+                    CMessage::UpdateResource {
+                        serial: rpc::next_serial(),
+                        resource: CResourceUpdate {
+                            id: id.clone(),
+                            priority: Some(priority),
+                            ..Default::default()
+                        },
+                    },
+                );
+                self.priority_pending = Some((id, priority));
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) if keys.is(Action::Move, k) => {
+                let (id, path) = match self.selected_torrent() {
+                    Some(t) => (t.id.clone(), t.path.clone()),
+                    None => return InputResult::Key(k),
+                };
+                let sink = Arc::clone(&self.sink);
+                // The details panel picks the new path up from the server's update
+                let prompt = Prompt::new("Path".to_owned(), path, move |path| {
+                    if path.is_empty() || !Path::new(path).is_absolute() {
+                        return Err("The path must be absolute".to_owned());
+                    }
+                    rpc::send(
+                        &sink,
+                        CMessage::UpdateResource {
+                            serial: rpc::next_serial(),
+                            resource: CResourceUpdate {
+                                id: id.clone(),
+                                path: Some(path.to_owned()),
+                                ..Default::default()
+                            },
+                        },
+                    );
+                    Ok(())
+                });
+                return InputResult::ReplaceWith(Box::new(widgets::OwnedOverlay::new(
+                    prompt,
+                    Box::new(self.clone()),
+                    (cmp::min(width.saturating_sub(4), 80), 2),
+                    None,
+                    "Move torrent".to_owned(),
+                )) as Box<dyn Component>);
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) if keys.is(Action::Rename, k) => {
+                // synapse has no notion of renaming torrents, so the name is kept in the user data
+                let prompt = match self.selected_torrent() {
+                    Some(t) => {
+                        self.user_data_prompt(t, "Name", "name", fmt::torrent_name(t).to_owned())
                     }
-                    if tree.is_empty() {
-                        return None;
+                    None => return InputResult::Key(k),
+                };
+                return InputResult::ReplaceWith(Box::new(widgets::OwnedOverlay::new(
+                    prompt,
+                    Box::new(self.clone()),
+                    (cmp::min(width.saturating_sub(4), 80), 1),
+                    None,
+                    "Rename torrent".to_owned(),
+                )) as Box<dyn Component>);
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) if keys.is(Action::Label, k) => {
+                let labels = self
+                    .torrents
+                    .2
+                    .iter()
+                    .chain(self.filtered.iter())
+                    .filter_map(|t| fmt::torrent_label(t).map(|l| l.to_owned()))
+                    .collect();
+                // The list shows the new label once the server echoes the user data
+                let prompt = match self.selected_torrent() {
+                    Some(t) => {
+                        let label = fmt::torrent_label(t).unwrap_or("").to_owned();
+                        self.user_data_prompt(t, "Label", "label", label)
                     }
+                    None => return InputResult::Key(k),
+                }
+                .completions(labels);
+                return InputResult::ReplaceWith(Box::new(widgets::OwnedOverlay::new(
+                    prompt,
+                    Box::new(self.clone()),
+                    (cmp::min(width.saturating_sub(4), 80), 1),
+                    None,
+                    "Label torrent".to_owned(),
+                )) as Box<dyn Component>);
+            }
 
-                    let draw = |target: &mut _, width, _, x, y, state: &mut Vec<String>| {
-                        for (i, e) in state.iter().enumerate() {
-                            widgets::Text::<_, align::x::Left, align::y::Top>::new(true, &**e)
-                                .render(target, width, 1, x, y + i as u16);
-                        }
-                    };
+            (k, Focus::Torrents) if keys.is(Action::Goto, k) => {
+                self.goto = Some(widgets::Input::with_capacity(8));
+            }
 
-                    let tlen = tree.len() as _;
-                    // FIXME: Cloning self here is pretty hacky
-                    Some(InputResult::ReplaceWith(
-                        Box::new(widgets::OwnedOverlay::new(
-                            widgets::CloseOnInput::new(
-                                widgets::IgnoreRpc::new(widgets::RenderStateFn::new(draw, tree)),
-                                &[],
-                            ),
-                            Box::new(self.clone()),
-                            (len, tlen),
-                            Some(ColorEscape::red()),
-                            "Errors".to_owned(),
-                        )) as Box<Component>,
-                    ))
-                })
-                .unwrap_or(InputResult::Key(Key::Char('e')));
+            (k, Focus::Torrents) if keys.is(Action::Search, k) => {
+                self.search.0 = Some(widgets::Input::with_capacity(16));
             }
 
-            (Key::Char('f'), Focus::Torrents) | (Key::Char('f'), Focus::Details) => {
+            (k, Focus::Torrents) | (k, Focus::Details) if keys.is(Action::Filter, k) => {
                 self.focus = Focus::Filter;
                 if !self.filter_disp {
                     self.filter_disp = true;
                 }
             }
 
-            (Key::Char('J'), Focus::Torrents) if !self.details.1.is_empty() => {
+            (k, Focus::Torrents) if keys.is(Action::FocusDown, k) && !self.details.1.is_empty() => {
                 self.focus = Focus::Details;
             }
 
-            (Key::Char('K'), Focus::Details) => {
+            (k, Focus::Details) if keys.is(Action::FocusUp, k) => {
                 self.focus = Focus::Torrents;
             }
 
-            (Key::Char('q'), Focus::Details) => {
+            (k, Focus::Details) if keys.is(Action::Close, k) => {
                 // This is ok, because details only focused when not empty
                 self.details.1.remove(self.details.0);
                 if self.details.1.is_empty() {
                     self.focus = Focus::Torrents;
-                    self.recompute_torrent_bounds(torr_list_height.saturating_sub(5));
+                    self.recompute_torrent_bounds(
+                        torr_list_height.saturating_sub(self.details_height),
+                    );
                 } else if self.details.0 == self.details.1.len() {
                     self.details.0 -= 1;
                 }
             }
 
-            (Key::Char('t'), Focus::Torrents) | (Key::Char('t'), Focus::Details) => {
+            (k, Focus::Trackers) if keys.is(Action::Shrink, k) || keys.is(Action::Grow, k) => {
+                let step = if keys.is(Action::Grow, k) {
+                    TRACKERS_WIDTH_STEP
+                } else {
+                    -TRACKERS_WIDTH_STEP
+                };
+                self.trackers_width =
+                    (self.trackers_width + step).clamp(MIN_TRACKERS_WIDTH, MAX_TRACKERS_WIDTH);
+                self.remember_layout();
+            }
+            (k, Focus::Details) if keys.is(Action::Shrink, k) || keys.is(Action::Grow, k) => {
+                self.details_height = if keys.is(Action::Grow, k) {
+                    cmp::min(self.details_height + 1, MAX_DETAILS_HEIGHT)
+                } else {
+                    cmp::max(self.details_height - 1, MIN_DETAILS_HEIGHT)
+                };
+                self.recompute_torrent_bounds(torr_list_height);
+                self.remember_layout();
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) | (k, Focus::Trackers)
+                if keys.is(Action::Totals, k) =>
+            {
+                self.totals_disp = !self.totals_disp;
+                self.recompute_torrent_bounds(torr_list_height);
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) | (k, Focus::Trackers)
+                if keys.is(Action::AbsoluteTimes, k) =>
+            {
+                self.absolute_times = !self.absolute_times;
+                for dt in &mut self.details.1 {
+                    dt.set_absolute_times(self.absolute_times);
+                }
+            }
+
+            (k, Focus::Torrents) | (k, Focus::Details) | (k, Focus::Trackers)
+                if keys.is(Action::Trackers, k) =>
+            {
+                if self.focus == Focus::Trackers {
+                    self.focus = Focus::Torrents;
+                }
                 self.trackers_disp = !self.trackers_disp;
-                self.recompute_torrent_bounds(torr_list_height.saturating_sub(5));
+                self.recompute_torrent_bounds(torr_list_height.saturating_sub(self.details_height));
+            }
+
+            (k, Focus::Torrents)
+                if keys.is(Action::FocusLeft, k)
+                    && self.trackers_disp
+                    && !self.trackers.is_empty() =>
+            {
+                self.focus = Focus::Trackers;
+            }
+
+            (k, Focus::Trackers) if k == Key::Esc || keys.is(Action::FocusRight, k) => {
+                self.focus = Focus::Torrents;
+            }
+
+            (k, Focus::Trackers)
+                if (k == Key::Up || keys.is(Action::Up, k)) && self.tracker_sel.1 > 0 =>
+            {
+                if self.tracker_sel.0 == self.tracker_sel.1 {
+                    self.tracker_sel.0 -= 1;
+                }
+                self.tracker_sel.1 -= 1;
+            }
+
+            (k, Focus::Trackers)
+                if (k == Key::Down || keys.is(Action::Down, k))
+                    && self.tracker_sel.1 + 1 < self.trackers.len() =>
+            {
+                if self.tracker_sel.0 + torr_height.saturating_sub(1) == self.tracker_sel.1 {
+                    self.tracker_sel.0 += 1;
+                }
+                self.tracker_sel.1 += 1;
+            }
+
+            (k, Focus::Trackers) if keys.is(Action::TrackerSort, k) => {
+                let sel = self.selected_tracker().map(|t| t.0.url.clone());
+                self.tracker_sort = self.tracker_sort.next();
+                self.sort_trackers(sel);
+                notify::push(format!("Sorting trackers by {}", self.tracker_sort.name()));
+            }
+
+            (Key::Home, Focus::Trackers) => {
+                self.tracker_sel = (0, 0);
+            }
+            (Key::End, Focus::Trackers) => {
+                let l = self.trackers.len();
+                self.tracker_sel = (l.saturating_sub(torr_height), l.saturating_sub(1));
+            }
+
+            (k, Focus::Trackers) if keys.is(Action::Reannounce, k) => {
+                // A row groups the trackers of all torrents with the same URL
+                let (ref base, ref others) = match self.selected_tracker() {
+                    Some(row) => row,
+                    None => return InputResult::Key(k),
+                };
+                for id in Some(&base.id)
+                    .into_iter()
+                    .chain(others.iter().map(|o| &o.0))
+                {
+                    rpc::send(
+                        &self.sink,
+                        CMessage::UpdateTracker {
+                            serial: rpc::next_serial(),
+                            id: id.clone(),
+                        },
+                    );
+                }
+                notify::push(format!("Reannouncing to {}", tracker_host(base)));
+            }
+
+            (Key::Char('\t'), Focus::Filter) => {
+                let hosts = self
+                    .trackers
+                    .iter()
+                    .filter_map(|(t, _)| t.url.host_str())
+                    .collect::<Vec<_>>();
+                let res = self.filter.complete(&hosts);
+                self.refilter();
+                return res;
             }
 
             // Catch all filter input
             (k, Focus::Filter) => {
-                return self.filter.input(k, width, height);
+                let res = self.filter.input(k, width, height);
+                self.refilter();
+                return res;
             }
 
             // Bounce unused
@@ -397,15 +1654,39 @@ impl HandleInput for Main {
         }
         InputResult::Rerender
     }
+
+    fn paste(&mut self, text: &str, width: u16, height: u16) -> InputResult {
+        if let Some(ref mut goto) = self.goto {
+            let digits = text
+                .chars()
+                .filter(char::is_ascii_digit)
+                .collect::<String>();
+            goto.push_str(&digits);
+            InputResult::Rerender
+        } else if let Some(ref mut search) = self.search.0 {
+            search.push_str(text);
+            InputResult::Rerender
+        } else if self.focus == Focus::Filter {
+            let res = self.filter.paste(text, width, height);
+            self.refilter();
+            res
+        } else {
+            InputResult::Rerender
+        }
+    }
 }
 
 impl Renderable for Main {
     fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
         // If the window got downsized, we need to tighten the torrent selection
         let d = self.torrents.1 - self.torrents.0;
-        let sub = if self.details.1.is_empty() { 0 } else { 6 };
+        let sub = if self.details.1.is_empty() {
+            0
+        } else {
+            self.details_height + 1
+        };
         // - 2 because of the server footer, -1 because of 1-0 index conversion
-        let torr_height = height.saturating_sub(3 + sub) as usize;
+        let torr_height = height.saturating_sub(3 + sub + self.extra_lines()) as usize;
         if d > torr_height {
             self.torrents.1 -= d - torr_height;
         }
@@ -414,7 +1695,63 @@ impl Renderable for Main {
             self.recompute_torrent_bounds(height);
         }
 
-        let draw_torrents = |target: &mut _, width: u16, height, x, y| {
+        let draw_torrents = |target: &mut _, width: u16, height: u16, x, y| {
+            let stats_fit = CONFIG.row_format.is_none()
+                && self.torrent_widths.5 != 0
+                && width.saturating_sub(self.torrent_widths.5 as u16 + 1) >= width / 3;
+            let (height, y) = if self.torrent_header {
+                widgets::Text::<_, align::x::Left, align::y::Top>::new(
+                    true,
+                    format!("{}Name{}", style::Bold, style::Reset),
+                )
+                .render(target, width, 1, x, y);
+                if stats_fit {
+                    widgets::Text::<_, align::x::Right, align::y::Top>::new(
+                        true,
+                        format!("{}{}{}", style::Bold, self.torrent_header(), style::Reset),
+                    )
+                    .render(
+                        target,
+                        cmp::min(self.torrent_widths.5 as u16, width),
+                        1,
+                        x + width.saturating_sub(self.torrent_widths.5 as u16),
+                        y,
+                    );
+                }
+                (height.saturating_sub(1), y + 1)
+            } else {
+                (height, y)
+            };
+            // Drawn like the header, so it is neither selected nor scrolled
+            let (height, y) = if self.aggregate_row && height > 0 {
+                widgets::Text::<_, align::x::Left, align::y::Top>::new(
+                    true,
+                    format!(
+                        "{}All {} torrents{}",
+                        style::Bold,
+                        self.torrents.2.len(),
+                        style::Reset
+                    ),
+                )
+                .render(target, width, 1, x, y);
+                if stats_fit {
+                    widgets::Text::<_, align::x::Right, align::y::Top>::new(
+                        true,
+                        self.aggregate_stats(),
+                    )
+                    .render(
+                        target,
+                        cmp::min(self.torrent_widths.5 as u16, width),
+                        1,
+                        x + width.saturating_sub(self.torrent_widths.5 as u16),
+                        y,
+                    );
+                }
+                (height - 1, y + 1)
+            } else {
+                (height, y)
+            };
+
             for (i, t) in self
                 .torrents
                 .2
@@ -423,108 +1760,142 @@ impl Renderable for Main {
                 .take(height as _)
                 .enumerate()
             {
-                let tracker_err = self
-                    .trackers
-                    .iter()
-                    .filter(|tra| {
-                        t.tracker_urls
-                            .iter()
-                            .any(|tu| *tu == tra.0.url.host_str().unwrap())
-                    })
-                    .any(|(ref base, ref others)| {
-                        base.error.is_some()
-                            || others
-                                .iter()
-                                .any(|&(_, ref id, ref e)| t.id == *id && e.is_some())
-                    });
+                let tracker_err = self.tracker_error(t);
 
-                let (c_s, c_e) = match self.focus {
-                    Focus::Torrents
-                        if i + self.torrents.0 == self.torrents.1
-                            && (t.error.is_some() || tracker_err) =>
-                    {
+                let selected =
+                    self.focus == Focus::Torrents && i + self.torrents.0 == self.torrents.1;
+                let error = t.error.is_some() || tracker_err;
+
+                let (c_s, c_e) = if selected {
+                    (
+                        format!("{}", ColorEscape::selection()),
+                        format!("{}", ColorEscape::reset()),
+                    )
+                } else if error {
+                    (
+                        format!("{}", ColorEscape::error()),
+                        format!("{}", ColorEscape::reset()),
+                    )
+                } else if self.added.contains_key(&t.id) {
+                    (
+                        format!("{}", ColorEscape::added()),
+                        format!("{}", ColorEscape::reset()),
+                    )
+                } else {
+                    ("".into(), "".into())
+                };
+                // The background fills the whole row, so that the gap between the name and the
+                // stats doesn't break it up. A highlight takes precedence over the shading
+                let bg = if selected && error {
+                    Some(ColorEscape::error_bg())
+                } else if selected && CONFIG.theme.selection_bg != Color::Reset {
+                    Some(ColorEscape::selection_bg())
+                } else if self.zebra && i % 2 == 1 {
+                    Some(ColorEscape::zebra())
+                } else {
+                    None
+                };
+                let (c_s, c_e) = match bg {
+                    Some(bg) => {
+                        widgets::Text::<_, align::x::Left, align::y::Top>::new(
+                            true,
+                            format!(
+                                "{}{}{}",
+                                bg,
+                                " ".repeat(width as _),
+                                ColorEscape::reset_bg()
+                            ),
+                        )
+                        .render(target, width, 1, x, y + i as u16);
                         (
-                            format!("{}{}", ColorEscape::cyan(), ColorEscape::red_bg()),
-                            format!("{}{}", ColorEscape::reset(), ColorEscape::reset_bg()),
+                            format!("{}{}", bg, c_s),
+                            format!("{}{}", c_e, ColorEscape::reset_bg()),
                         )
                     }
-                    Focus::Torrents if i + self.torrents.0 == self.torrents.1 => (
-                        format!("{}", ColorEscape::cyan()),
-                        format!("{}", ColorEscape::reset()),
-                    ),
-                    _ if t.error.is_some() || tracker_err => (
-                        format!("{}", ColorEscape::red()),
-                        format!("{}", ColorEscape::reset()),
-                    ),
-                    _ => ("".into(), "".into()),
+                    None => (c_s, c_e),
                 };
 
-                let (render_stats, width_left) =
-                    if width.saturating_sub(self.torrent_widths.4 as u16 + 1) < width / 3 {
-                        (false, width)
-                    } else {
-                        (true, width.saturating_sub(self.torrent_widths.4 as u16 + 1))
-                    };
+                let (render_stats, width_left) = if stats_fit {
+                    (true, width.saturating_sub(self.torrent_widths.5 as u16 + 1))
+                } else {
+                    (false, width)
+                };
+
+                if let Some(ref row_format) = CONFIG.row_format {
+                    let row = row_format.render(t);
+                    widgets::Text::<_, align::x::Left, align::y::Top>::new(
+                        true,
+                        if self.search_matches(t) {
+                            format!(
+                                "{}{}{}{}{}",
+                                c_s,
+                                style::Underline,
+                                row,
+                                style::NoUnderline,
+                                c_e
+                            )
+                        } else {
+                            format!("{}{}{}", c_s, row, c_e)
+                        },
+                    )
+                    .render(target, width, 1, x, y + i as u16);
+                    continue;
+                }
 
+                let label = fmt::torrent_label(t)
+                    .map(|l| format!("[{}] ", l))
+                    .unwrap_or_default();
+                // Cut off before the styling is added, which the helper can't skip
+                let name = match CONFIG.name_truncation {
+                    Truncation::End => Cow::Borrowed(fmt::torrent_name(t)),
+                    Truncation::Middle => utils::truncate_middle(
+                        fmt::torrent_name(t),
+                        (width_left as usize).saturating_sub(utils::count(&label)),
+                    ),
+                };
                 widgets::Text::<_, align::x::Left, align::y::Top>::new(
                     true,
-                    format!(
-                        "{}{}{}",
-                        c_s,
-                        &**t.name.as_ref().unwrap_or_else(|| &t.path),
-                        c_e
-                    ),
+                    if self.search_matches(t) {
+                        format!(
+                            "{}{}{}{}{}{}",
+                            c_s,
+                            label,
+                            style::Underline,
+                            name,
+                            style::NoUnderline,
+                            c_e
+                        )
+                    } else {
+                        format!("{}{}{}{}", c_s, label, name, c_e)
+                    },
                 )
                 .render(target, width_left, 1, x, y + i as u16);
                 if render_stats {
                     widgets::Text::<_, align::x::Right, align::y::Top>::new(
                         true,
-                        format!(
-                            "{}{: >3}% {: ^w_status$} {}[{: ^w_tu$}]↑ {}[{: ^w_td$}]↓   \
-                             {: >w_rat$.2}  {}↑  {}↓{}",
-                            c_s,
-                            (t.progress * 100.).round(),
-                            t.status.as_str(),
-                            t.rate_up.fmt_size_align(),
-                            t.throttle_up
-                                .map(|t| if t == -1 {
-                                    "∞".into()
-                                } else {
-                                    t.fmt_size_align()
-                                })
-                                .unwrap_or_else(|| "*".into()),
-                            t.rate_down.fmt_size(),
-                            t.throttle_down
-                                .map(|t| if t == -1 {
-                                    "∞".into()
-                                } else {
-                                    t.fmt_size_align()
-                                })
-                                .unwrap_or_else(|| "*".into()),
-                            if t.transferred_down == 0 {
-                                0.
-                            } else {
-                                t.transferred_up as f32 / t.transferred_down as f32
-                            },
-                            t.transferred_up.fmt_size_align(),
-                            t.transferred_down.fmt_size_align(),
-                            c_e,
-                            w_status = self.torrent_widths.0,
-                            w_tu = self.torrent_widths.1,
-                            w_td = self.torrent_widths.2,
-                            w_rat = self.torrent_widths.3,
-                        ),
+                        format!("{}{}{}", c_s, self.torrent_stats(t), c_e),
                     )
                     .render(
                         target,
-                        cmp::min(self.torrent_widths.4 as u16, width),
+                        cmp::min(self.torrent_widths.5 as u16, width),
                         1,
-                        x + width.saturating_sub(self.torrent_widths.4 as u16),
+                        x + width.saturating_sub(self.torrent_widths.5 as u16),
                         y + i as u16,
                     );
                 }
             }
             if self.filter_disp {
+                // Torrents hidden by client-side criteria are still known, so they can be counted
+                let count = if self.filter.is_client_side() {
+                    format!(
+                        "showing {} of {}",
+                        self.torrents.2.len(),
+                        self.torrents.2.len() + self.filtered.len()
+                    )
+                } else {
+                    format!("showing {}", self.torrents.2.len())
+                };
+                let count_w = cmp::min(count.len() as u16 + 1, width / 3);
                 widgets::Text::<_, align::x::Left, align::y::Top>::new(
                     true,
                     match self.focus {
@@ -532,56 +1903,97 @@ impl Renderable for Main {
                         _ => self.filter.format(false),
                     },
                 )
-                .render(target, width, 1, x, height);
+                .render(target, width - count_w, 1, x, height);
+                // Make it obvious that the list is filtered
+                let (c_s, c_e) = if self.filter.query().is_empty() {
+                    (ColorEscape::empty(), ColorEscape::empty())
+                } else {
+                    (ColorEscape::selection(), ColorEscape::reset())
+                };
+                widgets::Text::<_, align::x::Right, align::y::Top>::new(
+                    true,
+                    format!("{}{}{}", c_s, count, c_e),
+                )
+                .render(target, count_w, 1, x + width - count_w, height);
             }
         };
+        // The server footer, the totals, and the divider above them
+        let footer_height = 2 + self.totals_disp as u16;
         let draw_trackers = |target: &mut _, width, height, x, y| {
             let sel_tor = match self.focus {
-                Focus::Torrents | Focus::Filter => self.torrents.2.get(self.torrents.1),
+                Focus::Torrents | Focus::Filter | Focus::Trackers => {
+                    self.torrents.2.get(self.torrents.1)
+                }
                 Focus::Details => self.details.1.get(self.details.0).map(|t| t.inner()),
             };
-            for (i, &(ref base, ref others)) in self.trackers.iter().take(height as _).enumerate() {
+            // The lower bound may lag behind if the terminal was resized
+            let skip = cmp::max(
+                self.tracker_sel.0,
+                (self.tracker_sel.1 + 1).saturating_sub(height as usize),
+            );
+            for (i, &(ref base, ref others)) in self
+                .tracker_order
+                .iter()
+                .map(|&i| &self.trackers[i])
+                .skip(skip)
+                .take(height as _)
+                .enumerate()
+            {
                 let matches = sel_tor
                     .as_ref()
-                    .map(|t| {
-                        t.tracker_urls
-                            .iter()
-                            .any(|u| *u == base.url.host_str().unwrap())
-                    })
+                    .map(|t| t.tracker_urls.iter().any(|u| *u == tracker_host(base)))
                     .unwrap_or(false);
                 let (c_s, c_e) = match (
                     matches,
                     (base.error.is_some()
                         && base.torrent_id == sel_tor.map(|t| &*t.id).unwrap_or(""))
-                        || others.iter().any(|&(_, ref id, ref e)| {
+                        || others.iter().any(|&(_, ref id, ref e, _)| {
                             sel_tor.map(|t| t.id == *id).unwrap_or(false) && e.is_some()
                         }),
                 ) {
                     (true, true) => (
-                        format!("{}{}", ColorEscape::cyan(), ColorEscape::red_bg()),
+                        format!("{}{}", ColorEscape::selection(), ColorEscape::error_bg()),
                         format!("{}{}", ColorEscape::reset(), ColorEscape::reset_bg()),
                     ),
                     (true, false) => (
-                        format!("{}", ColorEscape::cyan()),
+                        format!("{}", ColorEscape::selection()),
                         format!("{}", ColorEscape::reset()),
                     ),
                     (false, true) => (
-                        format!("{}", ColorEscape::red()),
+                        format!("{}", ColorEscape::error()),
                         format!("{}", ColorEscape::reset()),
                     ),
                     (false, false) => ("".into(), "".into()),
                 };
-                widgets::Text::<_, align::x::Left, align::y::Top>::new(
-                    true,
+                let (c_s, c_e) = if self.focus == Focus::Trackers && i + skip == self.tracker_sel.1
+                {
+                    (
+                        format!("{}{}", c_s, style::Invert),
+                        format!("{}{}", style::NoInvert, c_e),
+                    )
+                } else {
+                    (c_s, c_e)
+                };
+                let line = if width < COMPACT_TRACKERS_COLUMNS {
+                    let error = base.error.is_some() || others.iter().any(|o| o.2.is_some());
                     format!(
-                        "{}{} {}{}",
+                        "{}{}{}{}{}",
                         c_s,
                         others.len() + 1,
-                        base.url.host_str().unwrap(),
+                        if error { '!' } else { ' ' },
+                        tracker_tag(base),
                         c_e,
-                    ),
-                )
-                .render(target, width, 1, x, y + i as u16);
+                    )
+                } else {
+                    format!("{}{} {}{}", c_s, others.len() + 1, tracker_host(base), c_e)
+                };
+                widgets::Text::<_, align::x::Left, align::y::Top>::new(true, line).render(
+                    target,
+                    width,
+                    1,
+                    x,
+                    y + i as u16,
+                );
             }
         };
         let draw_details = |target: &mut _, width, height, x, y| {
@@ -598,44 +2010,132 @@ impl Renderable for Main {
             )
             .render(target, width, height, x, y);
         };
-        let draw_footer = |target: &mut _, width, height, x, y| {
-            widgets::Text::<_, align::x::Left, align::y::Top>::new(
-                true,
-                format!(
-                    "Server {}: {} {}↑,   {}[{}]↑ {}[{}]↓,   \
-                     Session: {}↑ {}↓ → {:.2},   Lifetime: {}↑ {}↓ → {:.2}",
+        let draw_footer =
+            |target: &mut _, width, height: u16, x, y| {
+                let (height, y) = if self.totals_disp {
+                    widgets::Text::<_, align::x::Left, align::y::Top>::new(true, self.totals())
+                        .render(target, width, 1, x, y);
+                    (height.saturating_sub(1), y + 1)
+                } else {
+                    (height, y)
+                };
+                if let Some(ref goto) = self.goto {
+                    widgets::Text::<_, align::x::Left, align::y::Top>::new(
+                        true,
+                        format!(
+                            "{}Goto{}: {}",
+                            ColorEscape::selection(),
+                            ColorEscape::reset(),
+                            goto.format_active()
+                        ),
+                    )
+                    .render(target, width, height, x, y);
+                    return;
+                }
+                if let Some(ref search) = self.search.0 {
+                    widgets::Text::<_, align::x::Left, align::y::Top>::new(
+                        true,
+                        format!(
+                            "{}/{}{}   {} matching",
+                            ColorEscape::selection(),
+                            ColorEscape::reset(),
+                            search.format_active(),
+                            self.torrents
+                                .2
+                                .iter()
+                                .filter(|t| self.search_matches(t))
+                                .count()
+                        ),
+                    )
+                    .render(target, width, height, x, y);
+                    return;
+                }
+
+                // Show the rate sparklines to the right, if there is enough space
+                let spark_w = cmp::min(width / 4, RATE_SAMPLES as u16 * 2 + 5);
+                let width = if spark_w >= 12 {
+                    let w = (spark_w - 5) / 2;
+                    let x_spark = x + width - spark_w;
+                    widgets::Text::<_, align::x::Left, align::y::Top>::new(true, "↑")
+                        .render(target, 1, 1, x_spark, y);
+                    widgets::Sparkline::new(self.server_rates.iter().map(|r| r.0)).render(
+                        target,
+                        w,
+                        1,
+                        x_spark + 1,
+                        y,
+                    );
+                    widgets::Text::<_, align::x::Left, align::y::Top>::new(true, "↓").render(
+                        target,
+                        1,
+                        1,
+                        x_spark + w + 2,
+                        y,
+                    );
+                    widgets::Sparkline::new(self.server_rates.iter().map(|r| r.1)).render(
+                        target,
+                        w,
+                        1,
+                        x_spark + w + 3,
+                        y,
+                    );
+                    width - spark_w
+                } else {
+                    width
+                };
+                let stats = format!(
+                    "Server {}: {} {},   {}[{}]↑ {}[{}]↓,   \
+                     Session: {}↑ {}↓ → {},   Lifetime: {}↑ {}↓ → {}",
                     self.server_version,
-                    self.server.free_space.fmt_size(),
-                    fmt::date_diff_now(self.server.started),
-                    self.server.rate_up.fmt_size(),
+                    if self.space_low {
+                        format!(
+                            "{}{}{}",
+                            ColorEscape::error(),
+                            self.server.free_space.fmt_size(),
+                            ColorEscape::reset()
+                        )
+                    } else {
+                        self.server.free_space.fmt_size()
+                    },
+                    if self.absolute_times {
+                        format!("since {}", fmt::fmt_datetime_local(self.server.started))
+                    } else {
+                        format!("{}↑", fmt::date_diff_now_compact(self.server.started))
+                    },
+                    self.server.rate_up.fmt_rate(),
                     self.server
                         .throttle_up
-                        .map(|t| if t == -1 { "∞".into() } else { t.fmt_size() })
+                        .map(|t| if t == -1 { "∞".into() } else { t.fmt_rate() })
                         .unwrap_or_else(|| "∞".into()),
-                    self.server.rate_down.fmt_size(),
+                    self.server.rate_down.fmt_rate(),
                     self.server
                         .throttle_down
-                        .map(|t| if t == -1 { "∞".into() } else { t.fmt_size() })
+                        .map(|t| if t == -1 { "∞".into() } else { t.fmt_rate() })
                         .unwrap_or_else(|| "∞".into()),
                     self.server.ses_transferred_up.fmt_size(),
                     self.server.ses_transferred_down.fmt_size(),
-                    if self.server.ses_transferred_down == 0 {
-                        1.
-                    } else {
-                        self.server.ses_transferred_up as f32
-                            / self.server.ses_transferred_down as f32
-                    },
+                    fmt::fmt_ratio(
+                        self.server.ses_transferred_up,
+                        self.server.ses_transferred_down
+                    ),
                     self.server.transferred_up.fmt_size(),
                     self.server.transferred_down.fmt_size(),
-                    if self.server.transferred_down == 0 {
-                        1.
+                    fmt::fmt_ratio(self.server.transferred_up, self.server.transferred_down),
+                );
+                // Show the torrents by status left of the sparklines, if the server stats still fit
+                let counts = self.status_counts();
+                let counts_w = utils::count_without_styling(&counts);
+                let width =
+                    if width >= counts_w + 1 + utils::count_without_styling(&stats) {
+                        widgets::Text::<_, align::x::Left, align::y::Top>::new(true, counts)
+                            .render(target, counts_w, 1, x + width - counts_w, y);
+                        width - counts_w - 1
                     } else {
-                        self.server.transferred_up as f32 / self.server.transferred_down as f32
-                    },
-                ),
-            )
-            .render(target, width, height, x, y);
-        };
+                        width
+                    };
+                widgets::Text::<_, align::x::Left, align::y::Top>::new(true, stats)
+                    .render(target, width, height, x, y);
+            };
 
         match (self.trackers_disp, self.details.1.is_empty()) {
             (false, true) => {
@@ -643,7 +2143,7 @@ impl Renderable for Main {
                     &mut widgets::RenderFn::new(draw_torrents) as &mut Renderable,
                     &mut widgets::RenderFn::new(draw_footer) as &mut Renderable,
                     None,
-                    widgets::Unit::Lines(height.saturating_sub(2)),
+                    widgets::Unit::Lines(height.saturating_sub(footer_height)),
                     true,
                 )
                 .render(target, width, height, x_off, y_off);
@@ -654,12 +2154,13 @@ impl Renderable for Main {
                         &mut widgets::RenderFn::new(draw_trackers) as &mut Renderable,
                         &mut widgets::RenderFn::new(draw_torrents) as &mut Renderable,
                         None,
-                        widgets::Unit::Percent(0.2),
+                        widgets::Unit::Percent(self.trackers_width),
                         true,
-                    ) as &mut Renderable,
+                    )
+                    .min_size(MIN_TRACKERS_COLUMNS) as &mut dyn Renderable,
                     &mut widgets::RenderFn::new(draw_footer) as &mut Renderable,
                     None,
-                    widgets::Unit::Lines(height.saturating_sub(2)),
+                    widgets::Unit::Lines(height.saturating_sub(footer_height)),
                     true,
                 )
                 .render(target, width, height, x_off, y_off);
@@ -670,12 +2171,15 @@ impl Renderable for Main {
                         &mut widgets::RenderFn::new(draw_torrents) as &mut Renderable,
                         &mut widgets::RenderFn::new(draw_details) as &mut Renderable,
                         None,
-                        widgets::Unit::Lines(height.saturating_sub(8)),
+                        widgets::Unit::Lines(
+                            height.saturating_sub(self.details_height + 1 + footer_height),
+                        ),
                         false,
-                    ) as &mut Renderable,
+                    )
+                    .min_size(MIN_DETAILS_HEIGHT) as &mut dyn Renderable,
                     &mut widgets::RenderFn::new(draw_footer) as &mut Renderable,
                     None,
-                    widgets::Unit::Lines(height.saturating_sub(2)),
+                    widgets::Unit::Lines(height.saturating_sub(footer_height)),
                     true,
                 )
                 .render(target, width, height, x_off, y_off);
@@ -688,16 +2192,21 @@ impl Renderable for Main {
                             &mut widgets::RenderFn::new(draw_torrents) as &mut Renderable,
                             &mut widgets::RenderFn::new(draw_details) as &mut Renderable,
                             None,
-                            widgets::Unit::Lines(height.saturating_sub(8)),
+                            widgets::Unit::Lines(
+                                height.saturating_sub(self.details_height + 1 + footer_height),
+                            ),
                             false,
-                        ) as &mut Renderable,
+                        )
+                        .min_size(MIN_DETAILS_HEIGHT)
+                            as &mut dyn Renderable,
                         None,
-                        widgets::Unit::Percent(0.2),
+                        widgets::Unit::Percent(self.trackers_width),
                         true,
-                    ) as &mut Renderable,
+                    )
+                    .min_size(MIN_TRACKERS_COLUMNS) as &mut dyn Renderable,
                     &mut widgets::RenderFn::new(draw_footer) as &mut Renderable,
                     None,
-                    widgets::Unit::Lines(height.saturating_sub(2)),
+                    widgets::Unit::Lines(height.saturating_sub(footer_height)),
                     true,
                 )
                 .render(target, width, height, x_off, y_off);
@@ -717,21 +2226,25 @@ impl HandleRpc for Main {
                 // Remove matching resources, and move selection up/left if a resource up/left was
                 // removed
                 let mut i = 0;
-                let mut dec = 0;
+                let mut dec = (0, 0);
                 let mut recomp_bounds = false;
                 // TODO: With Closure disjoint borrows, these could be moved inside the closure
                 let sel = self.torrents.1;
                 let lower = self.torrents.0;
                 let height = self.last_height;
                 self.torrents.2.retain(|t| {
+                    let idx = i;
                     i += 1;
                     if ids.contains(&t.id) {
-                        // The torrents are sorted, so we need to adjust the selection if it's above
-                        // in the list
-                        if i <= sel - dec && sel - dec != 0 {
-                            dec += 1;
-                            recomp_bounds = true;
-                        } else if i >= lower && i - lower <= height as usize {
+                        // The torrents are sorted, so we need to adjust the selection and lower
+                        // bound if it's above them in the list
+                        if idx < sel {
+                            dec.1 += 1;
+                        }
+                        if idx < lower {
+                            dec.0 += 1;
+                        }
+                        if idx < lower + height as usize {
                             recomp_bounds = true;
                         }
                         false
@@ -739,20 +2252,26 @@ impl HandleRpc for Main {
                         true
                     }
                 });
-                self.torrents.0 -= dec;
-                self.torrents.1 -= dec;
+                self.torrents.0 -= dec.0;
+                self.torrents.1 -= dec.1;
+                // The selected torrent may have been the last one
+                self.torrents.1 =
+                    cmp::min(self.torrents.1, self.torrents.2.len().saturating_sub(1));
+                self.torrents.0 = cmp::min(self.torrents.0, self.torrents.1);
+                self.filtered.retain(|t| !ids.contains(&t.id));
 
                 if recomp_bounds {
                     self.recompute_torrent_bounds(height);
                 }
 
                 i = 0;
-                dec = 0;
+                let mut dec = 0;
                 let sel = self.details.0;
                 self.details.1.retain(|t| {
+                    let idx = i;
                     i += 1;
                     if ids.contains(&t.inner().id) {
-                        if i <= sel && sel - dec != 0 {
+                        if idx < sel {
                             dec += 1;
                         }
                         false
@@ -761,11 +2280,13 @@ impl HandleRpc for Main {
                     }
                 });
                 self.details.0 -= dec;
+                self.details.0 = cmp::min(self.details.0, self.details.1.len().saturating_sub(1));
                 if self.details.1.is_empty() && self.focus == Focus::Details {
                     self.focus = Focus::Torrents;
                 }
 
                 // FIXME: Once drain_filter lands, use that
+                let sel_tracker = self.selected_tracker().map(|t| t.0.url.clone());
                 let mut idx = 0;
                 while idx < self.trackers.len() {
                     let mut rm = false;
@@ -773,7 +2294,7 @@ impl HandleRpc for Main {
                     {
                         let (ref mut base, ref mut others) = self.trackers[idx];
 
-                        others.retain(|&(ref id, _, _)| !ids.contains(&id));
+                        others.retain(|o| !ids.contains(&o.0));
 
                         if ids.contains(&base.id) {
                             if others.is_empty() {
@@ -783,6 +2304,7 @@ impl HandleRpc for Main {
                                 base.id = last.0;
                                 base.torrent_id = last.1;
                                 base.error = last.2;
+                                base.last_report = last.3;
                             }
                         }
                     }
@@ -793,30 +2315,42 @@ impl HandleRpc for Main {
                         idx += 1;
                     }
                 }
+                self.sort_trackers(sel_tracker);
+                self.refresh_details();
+                if self.trackers.is_empty() && self.focus == Focus::Trackers {
+                    self.focus = Focus::Torrents;
+                }
 
                 true
             }
-            SMessage::UpdateResources { resources, .. } => {
+            SMessage::UpdateResources { serial, resources } => {
                 let mut recomp_bounds = false;
+                let mut refilter = false;
+                let mut resort = Vec::new();
+                // New trackers and errors may change the order of the trackers
+                let sel_tracker = self.selected_tracker().map(|t| t.0.url.clone());
+                let mut resort_trackers = false;
                 'UPDATES: for upd in resources.into_iter() {
                     match upd {
                         // New resource insertion
                         SResourceUpdate::Resource(res) => match res.into_owned() {
                             Resource::Server(s) => {
                                 self.server = s;
+                                self.check_space();
                             }
                             Resource::Torrent(t) => {
-                                let empty = String::new();
-                                let idx = self
-                                    .torrents
-                                    .2
-                                    .binary_search_by(|probe| {
-                                        natord::compare_ignore_case(
-                                            probe.name.as_ref().unwrap_or_else(|| &empty),
-                                            t.name.as_ref().unwrap_or_else(|| &empty),
-                                        )
-                                    })
-                                    .unwrap_or_else(|e| e);
+                                // The torrents that existed before are sent in reply to the
+                                // subscription, later additions are pushed without a serial. A
+                                // torrent that was sent before only started matching the filter
+                                // again
+                                if self.seen.insert(t.id.clone()) && serial.is_none() {
+                                    self.added.insert(t.id.clone(), Instant::now());
+                                }
+                                if !self.filter.matches(&t) {
+                                    self.filtered.push(t);
+                                    continue;
+                                }
+                                let idx = sorted_pos(&self.torrents.2, &t);
 
                                 if idx >= self.torrents.0
                                     && idx - self.torrents.0 <= self.last_height as usize
@@ -827,6 +2361,7 @@ impl HandleRpc for Main {
                                 self.torrents.2.insert(idx, t);
                             }
                             Resource::Tracker(t) => {
+                                resort_trackers = true;
                                 let mut new_pos = self.trackers.len();
                                 for (i, &mut (ref mut base, ref mut others)) in
                                     self.trackers.iter_mut().enumerate()
@@ -834,9 +2369,12 @@ impl HandleRpc for Main {
                                     match t.url.cmp(&base.url) {
                                         Ordering::Equal => {
                                             let idx = others
-                                                .binary_search_by_key(&&t.id, |&(ref id, _, _)| id)
+                                                .binary_search_by_key(&&t.id, |o| &o.0)
                                                 .unwrap_or_else(|e| e);
-                                            others.insert(idx, (t.id, t.torrent_id, t.error));
+                                            others.insert(
+                                                idx,
+                                                (t.id, t.torrent_id, t.error, t.last_report),
+                                            );
                                             continue 'UPDATES;
                                         }
                                         Ordering::Less => {
@@ -863,7 +2401,23 @@ impl HandleRpc for Main {
                         | SResourceUpdate::ServerTransfer { .. }
                         | SResourceUpdate::ServerSpace { .. }
                         | SResourceUpdate::ServerToken { .. } => {
+                            // The server reports its rates along with the transfer every tick
+                            let (rate, space) = match upd {
+                                SResourceUpdate::ServerTransfer { .. } => (true, false),
+                                SResourceUpdate::ServerSpace { .. } => (false, true),
+                                _ => (false, false),
+                            };
                             self.server.update(upd);
+                            if space {
+                                self.check_space();
+                            }
+                            if rate {
+                                if self.server_rates.len() == RATE_SAMPLES {
+                                    self.server_rates.pop_front();
+                                }
+                                self.server_rates
+                                    .push_back((self.server.rate_up, self.server.rate_down));
+                            }
                         }
                         // Tracker updates
                         SResourceUpdate::TrackerStatus {
@@ -872,16 +2426,16 @@ impl HandleRpc for Main {
                             error,
                             ..
                         } => {
+                            resort_trackers = true;
                             for &mut (ref mut base, ref mut others) in &mut self.trackers {
                                 if id == base.id {
                                     base.last_report = last_report;
                                     base.error = error;
                                     break;
-                                } else if let Ok(pos) =
-                                    others.binary_search_by_key(&&id, |&(ref id, _, _)| id)
+                                } else if let Ok(pos) = others.binary_search_by_key(&&id, |o| &o.0)
                                 {
-                                    base.last_report = last_report;
                                     others[pos].2 = error;
+                                    others[pos].3 = last_report;
                                     break;
                                 }
                             }
@@ -901,15 +2455,43 @@ impl HandleRpc for Main {
                         | SResourceUpdate::TorrentPicker { .. }
                         | SResourceUpdate::TorrentPriority { .. }
                         | SResourceUpdate::TorrentPath { .. }
-                        | SResourceUpdate::TorrentPieces { .. } => {
-                            for t in self.details.1.iter_mut().map(|t| t.inner_mut()) {
-                                if upd.id() == &*t.id {
-                                    t.update(upd.clone());
-                                    // The id will also be in the torrent list
-                                    break;
+                        | SResourceUpdate::TorrentPieces { .. }
+                        | SResourceUpdate::UserData {
+                            kind: ResourceKind::Torrent,
+                            ..
+                        } => {
+                            // The status, ratio, and ETA column widths may need to change, e.g.
+                            // when hashing
+                            match upd {
+                                SResourceUpdate::TorrentStatus { .. }
+                                | SResourceUpdate::TorrentTransfer { .. }
+                                | SResourceUpdate::Rate { .. } => recomp_bounds = true,
+                                // Any priority the server settles on supersedes the requested one
+                                SResourceUpdate::TorrentPriority { ref id, .. } => {
+                                    let echoed = match self.priority_pending {
+                                        Some((ref p_id, _)) => p_id == id,
+                                        None => false,
+                                    };
+                                    if echoed {
+                                        self.priority_pending = None;
+                                    }
+                                }
+                                // The display name is kept in the user data
+                                SResourceUpdate::UserData { ref id, .. } => {
+                                    resort.push(id.clone());
+                                }
+                                _ => (),
+                            }
+                            // A changed ratio or name may move the torrent in or out of the
+                            // filtered ones
+                            match upd {
+                                SResourceUpdate::TorrentTransfer { .. }
+                                | SResourceUpdate::UserData { .. } => {
+                                    refilter = self.filter.is_client_side();
                                 }
+                                _ => (),
                             }
-                            for t in &mut self.torrents.2 {
+                            for t in self.torrents.2.iter_mut().chain(self.filtered.iter_mut()) {
                                 if upd.id() == &*t.id {
                                     t.update(upd);
                                     break;
@@ -920,12 +2502,29 @@ impl HandleRpc for Main {
                     }
                 }
 
-                if recomp_bounds {
+                self.refresh_details();
+                if resort_trackers {
+                    self.sort_trackers(sel_tracker);
+                }
+                let resorted = !resort.is_empty();
+                for id in resort {
+                    self.resort(&id);
+                }
+                if refilter {
+                    self.refilter();
+                } else if recomp_bounds || resorted {
                     let h = self.last_height;
                     self.recompute_torrent_bounds(h);
                 }
                 true
             }
+            // A rejected priority change will not be echoed
+            SMessage::InvalidRequest(_)
+            | SMessage::InvalidResource(_)
+            | SMessage::PermissionDenied(_) => {
+                self.priority_pending = None;
+                false
+            }
             _ => false,
         }
     }
@@ -0,0 +1,97 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use synapse_rpc::message::SMessage;
+use termion::event::Key;
+
+use crate::{
+    keymap::Action,
+    tui::{widgets, Component, HandleInput, HandleRpc, InputResult, Renderable},
+    utils::{self, align, color::ColorEscape},
+};
+
+/// Asks whether to run an action of the component, which is shown below. `y` runs it, any other
+/// key cancels. Quitting is run by the view, which then tears down the component
+pub struct Confirm {
+    question: String,
+    action: Action,
+    // Only taken when the overlay closes
+    below: Option<Box<dyn Component>>,
+}
+
+impl Confirm {
+    pub fn new(question: String, action: Action, below: Box<dyn Component>) -> Confirm {
+        Confirm {
+            question: format!("{} [y/n]", question),
+            action,
+            below: Some(below),
+        }
+    }
+
+    fn below(&mut self) -> &mut dyn Component {
+        &mut **self.below.as_mut().unwrap_or_else(|| unreachable!())
+    }
+}
+
+impl Component for Confirm {
+    fn transferring(&self) -> bool {
+        // Quitting was asked about already, so the quit key answers the question
+        self.action != Action::Quit && self.below.as_ref().is_some_and(|b| b.transferring())
+    }
+    fn teardown(&mut self) {
+        self.below().teardown();
+    }
+    fn expire(&mut self) -> bool {
+        self.below().expire()
+    }
+}
+
+impl Renderable for Confirm {
+    fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
+        let mut text =
+            widgets::Text::<_, align::x::Center, align::y::Top>::new(true, &*self.question);
+        let len = utils::count(&self.question) as u16;
+        widgets::BorrowedOverlay::new(
+            &mut text,
+            &mut **self.below.as_mut().unwrap_or_else(|| unreachable!()),
+            (len, 1),
+            Some(&ColorEscape::error()),
+            "Confirm",
+        )
+        .render(target, width, height, x_off, y_off);
+    }
+}
+
+impl HandleInput for Confirm {
+    fn input(&mut self, k: Key, w: u16, h: u16) -> InputResult {
+        if self.action == Action::Quit && k == Key::Char('y') {
+            return InputResult::Quit;
+        }
+        let mut below = self.below.take().unwrap_or_else(|| unreachable!());
+        if k == Key::Char('y') {
+            // The action may replace the panel itself, e.g. with an error overlay
+            if let InputResult::ReplaceWith(other) = below.input_confirmed(self.action, w, h) {
+                return InputResult::ReplaceWith(other);
+            }
+        }
+        InputResult::ReplaceWith(below)
+    }
+}
+
+impl HandleRpc for Confirm {
+    fn rpc(&mut self, msg: SMessage) -> bool {
+        self.below().rpc(msg)
+    }
+}
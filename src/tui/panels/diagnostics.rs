@@ -0,0 +1,102 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use synapse_rpc::message::SMessage;
+use termion::event::Key;
+
+use crate::{
+    logger, rpc,
+    tui::{widgets, Component, HandleInput, HandleRpc, InputResult, Renderable},
+    utils::{align, fmt},
+};
+
+/// Shows statistics about the rpc connection and the most recent log records, for debugging
+pub struct Diagnostics;
+
+// The number of log records shown
+const LOG_LINES: u16 = 8;
+
+// The number of lines rendered
+pub const HEIGHT: u16 = 5 + LOG_LINES;
+
+impl Component for Diagnostics {}
+
+impl Renderable for Diagnostics {
+    fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
+        let stats = rpc::stats();
+        let lines = [
+            format!("Serial: {}", rpc::current_serial()),
+            format!(
+                "Subscriptions: {} resources, {} filters",
+                stats.subscribed,
+                stats.filters.len()
+            ),
+            format!(
+                "Round-trip time: {}",
+                stats.rtt.map_or_else(
+                    || "-".to_owned(),
+                    |rtt| format!(
+                        "{}ms",
+                        rtt.as_secs() * 1000 + u64::from(rtt.subsec_millis())
+                    )
+                )
+            ),
+            format!(
+                "Connected for: {}",
+                stats
+                    .connected
+                    .map_or_else(|| "-".to_owned(), fmt::date_diff_now)
+            ),
+        ];
+        // Log records are only kept if they would otherwise be written to the terminal
+        let log = logger::deferred(LOG_LINES as usize);
+        let log_header = if log.is_empty() {
+            "Log: no deferred records".to_owned()
+        } else {
+            "Log:".to_owned()
+        };
+        for (i, line) in lines
+            .iter()
+            .chain(Some(&log_header))
+            .chain(&log)
+            .enumerate()
+            .take(height as usize)
+        {
+            widgets::Text::<_, align::x::Left, align::y::Top>::new(false, &**line).render(
+                target,
+                width,
+                1,
+                x_off,
+                y_off + i as u16,
+            );
+        }
+    }
+}
+
+impl HandleInput for Diagnostics {
+    fn input(&mut self, k: Key, _: u16, _: u16) -> InputResult {
+        match k {
+            Key::Esc => InputResult::Close,
+            _ => InputResult::Key(k),
+        }
+    }
+}
+
+impl HandleRpc for Diagnostics {
+    // Any message may change the statistics
+    fn rpc(&mut self, _: SMessage) -> bool {
+        true
+    }
+}
@@ -0,0 +1,195 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use synapse_rpc::message::SMessage;
+use termion::event::Key;
+
+use std::cmp;
+
+use crate::{
+    config::CONFIG,
+    keymap::Action,
+    tui::{widgets, Component, HandleInput, HandleRpc, InputResult, Renderable},
+    utils::{align, color::ColorEscape},
+};
+
+pub struct Errors {
+    lines: Vec<String>,
+    // The first visible line, and the height of the last render
+    offset: usize,
+    height: u16,
+    // The query being typed, and the last confirmed one
+    search: (Option<widgets::Input>, String),
+}
+
+impl Errors {
+    pub fn new(lines: Vec<String>) -> Errors {
+        Errors {
+            lines,
+            offset: 0,
+            height: 1,
+            search: (None, String::new()),
+        }
+    }
+
+    fn max_offset(&self) -> usize {
+        self.lines.len().saturating_sub(self.height as usize)
+    }
+
+    // Scroll to the next line after the first visible one that contains the query
+    fn find_next(&mut self) {
+        if self.search.1.is_empty() {
+            return;
+        }
+        let query = &self.search.1;
+        if let Some(pos) = self
+            .lines
+            .iter()
+            .enumerate()
+            .skip(self.offset + 1)
+            .chain(self.lines.iter().enumerate().take(self.offset + 1))
+            .find(|&(_, l)| l.contains(&**query))
+            .map(|(i, _)| i)
+        {
+            self.offset = cmp::min(pos, self.max_offset());
+        }
+    }
+}
+
+impl Component for Errors {
+    fn typing(&self) -> bool {
+        self.search.0.is_some()
+    }
+}
+
+impl Renderable for Errors {
+    fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
+        // The last line shows the search prompt while searching
+        let height = if self.search.0.is_some() {
+            height.saturating_sub(1)
+        } else {
+            height
+        };
+        self.height = cmp::max(height, 1);
+        self.offset = cmp::min(self.offset, self.max_offset());
+
+        for (i, l) in self
+            .lines
+            .iter()
+            .skip(self.offset)
+            .take(height as _)
+            .enumerate()
+        {
+            widgets::Text::<_, align::x::Left, align::y::Top>::new(true, &**l).render(
+                target,
+                width,
+                1,
+                x_off,
+                y_off + i as u16,
+            );
+        }
+
+        if let Some(ref search) = self.search.0 {
+            widgets::Text::<_, align::x::Left, align::y::Top>::new(
+                true,
+                format!(
+                    "{}/{}{}",
+                    ColorEscape::selection(),
+                    ColorEscape::reset(),
+                    search.format_active()
+                ),
+            )
+            .render(target, width, 1, x_off, y_off + height);
+        }
+    }
+}
+
+impl HandleInput for Errors {
+    fn input(&mut self, k: Key, _: u16, _: u16) -> InputResult {
+        if let Some(mut search) = self.search.0.take() {
+            match k {
+                Key::Esc => {}
+                Key::Char('\n') => {
+                    self.search.1 = search.inner().to_owned();
+                    self.find_next();
+                }
+                Key::Backspace => {
+                    search.backspace();
+                    self.search.0 = Some(search);
+                }
+                Key::Left => {
+                    search.cursor_left();
+                    self.search.0 = Some(search);
+                }
+                Key::Right => {
+                    search.cursor_right();
+                    self.search.0 = Some(search);
+                }
+                Key::Char(c) => {
+                    search.push(c);
+                    self.search.0 = Some(search);
+                }
+                _ => {
+                    self.search.0 = Some(search);
+                }
+            }
+            return InputResult::Rerender;
+        }
+
+        let keys = &CONFIG.keys;
+        match k {
+            k if k == Key::Esc || keys.is(Action::Close, k) => return InputResult::Close,
+            k if k == Key::Up || keys.is(Action::Up, k) => {
+                self.offset = self.offset.saturating_sub(1);
+            }
+            k if k == Key::Down || keys.is(Action::Down, k) => {
+                self.offset = cmp::min(self.offset + 1, self.max_offset());
+            }
+            Key::PageUp => {
+                self.offset = self.offset.saturating_sub(self.height as usize);
+            }
+            Key::PageDown => {
+                self.offset = cmp::min(self.offset + self.height as usize, self.max_offset());
+            }
+            Key::Home => {
+                self.offset = 0;
+            }
+            Key::End => {
+                self.offset = self.max_offset();
+            }
+            Key::Char('/') => {
+                self.search.0 = Some(widgets::Input::with_capacity(16));
+            }
+            Key::Char('n') => {
+                self.find_next();
+            }
+            _ => return InputResult::Key(k),
+        }
+        InputResult::Rerender
+    }
+
+    fn paste(&mut self, text: &str, _: u16, _: u16) -> InputResult {
+        if let Some(ref mut search) = self.search.0 {
+            search.push_str(text);
+        }
+        InputResult::Rerender
+    }
+}
+
+impl HandleRpc for Errors {
+    fn rpc(&mut self, _: SMessage) -> bool {
+        false
+    }
+}
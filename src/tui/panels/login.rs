@@ -13,13 +13,27 @@
 // You should have received a copy of the GNU General Public License
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
+use log::LevelFilter;
 use synapse_rpc::message::SMessage;
 use termion::{cursor, event::Key};
 
-use std::io::Write;
+use std::{
+    cmp,
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+static SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+// Whether a login panel showed the log warning already
+static LOG_WARNED: AtomicBool = AtomicBool::new(false);
 
 use crate::{
     config::CONFIG,
+    keymap::Action,
+    rpc,
+    state::State,
     tui::{widgets, Component, HandleInput, HandleRpc, InputResult, Renderable},
     utils::{
         align::{self, x::Align},
@@ -30,29 +44,95 @@ use crate::{
 #[derive(Clone)]
 pub struct Login {
     server: widgets::Input,
+    // Either the password or the token
     pass: widgets::PasswordInput,
+    token: bool,
     srv_selected: bool,
+    // Recently used servers, and which one is currently in the server field
+    recent: (Option<usize>, Vec<String>),
+    // Whether to warn that the log contains sensitive information
+    log_warning: bool,
+    // When the pending connection attempt was started, if there is one
+    connecting: Option<Instant>,
 }
 
 impl Login {
     pub fn new() -> Login {
         Login {
-            server: CONFIG
-                .server
-                .as_ref()
+            srv_selected: true,
+            ..Login::with_server(
+                CONFIG.server.as_ref(),
+                rpc::Auth::from_config(CONFIG.pass.as_ref(), CONFIG.token.as_ref()),
+            )
+        }
+    }
+
+    /// Prefills the fields, the server falls back to the most recently used one. If a server is
+    /// given, the password field is selected
+    pub fn with_server(server: Option<&String>, auth: Option<rpc::Auth>) -> Login {
+        let recent = State::load().servers;
+        Login {
+            server: server
+                .or_else(|| recent.first())
                 .map(|s| widgets::Input::from(s.clone(), s.len() + 1))
-                .unwrap_or_else(|| widgets::Input::from("ws://:8412".into(), 6)),
-            pass: CONFIG
-                .pass
+                .unwrap_or_else(|| widgets::Input::with_capacity(20)),
+            pass: auth
                 .as_ref()
-                .map(|s| widgets::PasswordInput::from(s.clone(), s.len() + 1))
+                .map(|a| widgets::PasswordInput::from(a.secret().to_owned(), a.secret().len() + 1))
                 .unwrap_or_else(|| widgets::PasswordInput::with_capacity(20)),
-            srv_selected: true,
+            token: matches!(auth, Some(rpc::Auth::Token(_))),
+            srv_selected: server.is_none(),
+            recent: (
+                if server.is_none() && !recent.is_empty() {
+                    Some(0)
+                } else {
+                    None
+                },
+                recent,
+            ),
+            // Key presses, and thus passwords, are logged at the debug level
+            log_warning: log::max_level() >= LevelFilter::Debug
+                && !LOG_WARNED.swap(true, Ordering::Relaxed),
+            connecting: None,
         }
     }
+
+    // Replace the server field with the next older, or newer, recently used server
+    fn cycle_recent(&mut self, older: bool) {
+        let pos = match (self.recent.0, older) {
+            (None, true) => 0,
+            (None, false) | (Some(0), false) => return,
+            (Some(p), true) => cmp::min(p + 1, self.recent.1.len() - 1),
+            (Some(p), false) => p - 1,
+        };
+        self.recent.0 = Some(pos);
+        let s = &self.recent.1[pos];
+        self.server = widgets::Input::from(s.clone(), s.len() + 1);
+    }
 }
 
-impl Component for Login {}
+/// A spinner and the time the pending connection attempt is taking, if there is one
+pub fn connecting_status(since: Option<Instant>) -> Option<String> {
+    since.map(|since| {
+        let elapsed = since.elapsed();
+        let frame = (elapsed.as_secs() * 10 + u64::from(elapsed.subsec_millis() / 100)) as usize;
+        format!(
+            "{} Connecting… {}s",
+            SPINNER[frame % SPINNER.len()],
+            elapsed.as_secs()
+        )
+    })
+}
+
+impl Component for Login {
+    // One of the fields always has focus
+    fn typing(&self) -> bool {
+        true
+    }
+    fn connecting(&mut self, since: Option<Instant>) {
+        self.connecting = since;
+    }
+}
 
 impl HandleRpc for Login {
     fn rpc(&mut self, _: SMessage) -> bool {
@@ -62,33 +142,56 @@ impl HandleRpc for Login {
 
 impl Renderable for Login {
     fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, _: u16, _: u16) {
+        let label = if self.token { "Token" } else { "Pass" };
         let (srv, pass) = if self.srv_selected {
             (
                 format!(
                     "{}Server{}: {}",
-                    ColorEscape::cyan(),
+                    ColorEscape::selection(),
                     ColorEscape::reset(),
                     self.server.format_active()
                 ),
-                format!("Pass: {}", self.pass.format_inactive()),
+                format!("{}: {}", label, self.pass.format_inactive()),
             )
         } else {
             (
                 format!("Server: {}", self.server.format_inactive()),
                 format!(
-                    "{}Pass{}: {}",
-                    ColorEscape::cyan(),
+                    "{}{}{}: {}",
+                    ColorEscape::selection(),
+                    label,
                     ColorEscape::reset(),
                     self.pass.format_active()
                 ),
             )
         };
-        let lines = &[
+        let status = connecting_status(self.connecting);
+        let mut lines = vec![
             "Welcome to axon, the synapse TUI",
             "Login to a synapse instance:",
             &srv,
             &pass,
         ];
+        if let Some(ref status) = status {
+            lines.push(status);
+        }
+        let warning = format!(
+            "{}Do not share the log publicly, it contains the typed password{}",
+            ColorEscape::error(),
+            ColorEscape::reset()
+        );
+        if self.log_warning {
+            lines.push(&warning);
+        }
+        let insecure = format!(
+            "{}Certificates of wss:// servers are not verified, the connection is insecure{}",
+            ColorEscape::error(),
+            ColorEscape::reset()
+        );
+        if CONFIG.tls.danger_accept_invalid_certs {
+            lines.push(&insecure);
+        }
+        let lines = &lines[..];
 
         write!(
             target,
@@ -96,7 +199,6 @@ impl Renderable for Login {
             cursor::Goto(
                 match align::x::CenterLongestLeft::align_offset(lines, width) {
                     align::x::Alignment::Single(x) => x,
-                    _ => unreachable!(),
                 },
                 height / 3
             )
@@ -107,7 +209,7 @@ impl Renderable for Login {
 }
 
 impl HandleInput for Login {
-    fn input(&mut self, k: Key, _: u16, _: u16) -> InputResult {
+    fn input(&mut self, k: Key, width: u16, _: u16) -> InputResult {
         match k {
             Key::Home => {
                 if self.srv_selected {
@@ -125,7 +227,14 @@ impl HandleInput for Login {
                 }
             }
 
-            Key::Down | Key::Up | Key::Char('\t') => {
+            Key::Up | Key::Down if self.srv_selected && !self.recent.1.is_empty() => {
+                self.cycle_recent(k == Key::Down);
+            }
+
+            k if k == Key::Down
+                || k == Key::Up
+                || CONFIG.keys.is_unless_typing(Action::SwitchField, k, true) =>
+            {
                 self.srv_selected = !self.srv_selected;
             }
 
@@ -145,6 +254,46 @@ impl HandleInput for Login {
                 }
             }
 
+            Key::Alt('b') => {
+                if self.srv_selected {
+                    self.server.word_left();
+                } else {
+                    self.pass.word_left();
+                }
+            }
+
+            Key::Alt('f') => {
+                if self.srv_selected {
+                    self.server.word_right();
+                } else {
+                    self.pass.word_right();
+                }
+            }
+
+            Key::Ctrl('w') => {
+                if self.srv_selected {
+                    self.server.delete_word();
+                } else {
+                    self.pass.delete_word();
+                }
+            }
+
+            Key::Ctrl('k') => {
+                if self.srv_selected {
+                    self.server.kill_to_end();
+                } else {
+                    self.pass.kill_to_end();
+                }
+            }
+
+            Key::Ctrl('u') => {
+                if self.srv_selected {
+                    self.server.kill_to_start();
+                } else {
+                    self.pass.kill_to_start();
+                }
+            }
+
             Key::Backspace => {
                 if self.srv_selected {
                     self.server.backspace();
@@ -161,10 +310,35 @@ impl HandleInput for Login {
                 }
             }
 
-            Key::Char('\n') => {
+            k if !self.srv_selected
+                && CONFIG
+                    .keys
+                    .is_unless_typing(Action::RevealPassword, k, true) =>
+            {
+                self.pass.toggle_revealed();
+            }
+
+            k if CONFIG.keys.is_unless_typing(Action::TokenAuth, k, true) => {
+                self.token = !self.token;
+            }
+
+            k if CONFIG.keys.is_unless_typing(Action::Connect, k, true) => {
+                if let Err(e) = rpc::server_url(self.server.inner()) {
+                    return InputResult::ReplaceWith(Box::new(widgets::error_overlay(
+                        "Url".to_owned(),
+                        format!("Invalid server URL: {}", e),
+                        Box::new(self.clone()),
+                        width.saturating_sub(4),
+                    )));
+                }
+                let secret = self.pass.inner().to_string();
                 return InputResult::ConnectWith(
                     self.server.inner().to_string(),
-                    self.pass.inner().to_string(),
+                    if self.token {
+                        rpc::Auth::Token(secret)
+                    } else {
+                        rpc::Auth::Password(secret)
+                    },
                 );
             }
 
@@ -181,4 +355,13 @@ impl HandleInput for Login {
         }
         InputResult::Rerender
     }
+
+    fn paste(&mut self, text: &str, _: u16, _: u16) -> InputResult {
+        if self.srv_selected {
+            self.server.push_str(text);
+        } else {
+            self.pass.push_str(text);
+        }
+        InputResult::Rerender
+    }
 }
@@ -0,0 +1,218 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use synapse_rpc::{
+    criterion::{Criterion, Operation, Value},
+    message::{CMessage, SMessage},
+    resource::{Peer, Resource, ResourceKind, SResourceUpdate},
+};
+use termion::event::Key;
+
+use std::{cmp, iter, sync::Arc};
+
+use crate::{
+    rpc,
+    tui::{widgets, Component, HandleInput, HandleRpc, InputResult, Renderable},
+    utils::{self, align, fmt::FormatSize},
+};
+
+// The columns, and whether they are aligned to the right
+const COLUMNS: [(&str, bool); 5] = [
+    ("IP", false),
+    ("Client", false),
+    ("Up", true),
+    ("Down", true),
+    ("Avail", true),
+];
+
+// Pads the cells to the widths of their columns, separated by a space
+fn format_row(cells: &[String], widths: &[usize]) -> String {
+    let mut row = String::new();
+    for (i, ((cell, &width), &(_, right))) in cells.iter().zip(widths).zip(&COLUMNS).enumerate() {
+        if i != 0 {
+            row.push(' ');
+        }
+        let pad = " ".repeat(width.saturating_sub(utils::count(cell)));
+        if right {
+            row.push_str(&pad);
+            row.push_str(cell);
+        } else {
+            row.push_str(cell);
+            row.push_str(&pad);
+        }
+    }
+    row
+}
+
+pub struct Peers {
+    torrent_id: String,
+    serial: u64,
+    sink: rpc::WsSink,
+    peers: Vec<Peer>,
+}
+
+impl Peers {
+    pub fn new(sink: &rpc::WsSink, torrent_id: String) -> Peers {
+        let serial = rpc::next_serial();
+        rpc::send(
+            sink,
+            CMessage::FilterSubscribe {
+                serial,
+                kind: ResourceKind::Peer,
+                criteria: vec![Criterion {
+                    field: "torrent_id".into(),
+                    op: Operation::Eq,
+                    value: Value::S(torrent_id.clone()),
+                }],
+            },
+        );
+
+        Peers {
+            torrent_id,
+            serial,
+            sink: Arc::clone(sink),
+            peers: Vec::new(),
+        }
+    }
+
+    fn unsubscribe(&mut self) {
+        rpc::send(
+            &self.sink,
+            CMessage::FilterUnsubscribe {
+                serial: rpc::next_serial(),
+                filter_serial: self.serial,
+            },
+        );
+        // The individual peers were subscribed to when they became extant
+        if !self.peers.is_empty() {
+            rpc::send(
+                &self.sink,
+                CMessage::Unsubscribe {
+                    serial: rpc::next_serial(),
+                    ids: self.peers.drain(..).map(|p| p.id).collect(),
+                },
+            );
+        }
+    }
+}
+
+impl Component for Peers {
+    fn teardown(&mut self) {
+        self.unsubscribe();
+    }
+}
+
+impl Renderable for Peers {
+    fn name(&self) -> String {
+        "Peers".to_owned()
+    }
+    fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
+        if self.peers.is_empty() {
+            widgets::Text::<_, align::x::Center, align::y::Center>::new(true, "No peers connected")
+                .render(target, width, height, x_off, y_off);
+            return;
+        }
+
+        self.peers
+            .sort_unstable_by_key(|p| cmp::Reverse(p.rate_down));
+
+        let header: Vec<_> = COLUMNS.iter().map(|&(name, _)| name.to_owned()).collect();
+        let rows: Vec<_> = self
+            .peers
+            .iter()
+            .take(height.saturating_sub(1) as _)
+            .map(|p| {
+                vec![
+                    p.ip.clone(),
+                    p.client_id.clone(),
+                    format!("{}↑", p.rate_up.fmt_rate_align()),
+                    format!("{}↓", p.rate_down.fmt_rate_align()),
+                    format!("{}%", (p.availability * 100.).round()),
+                ]
+            })
+            .collect();
+        let mut widths: Vec<_> = header.iter().map(|c| utils::count(c)).collect();
+        for row in &rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = cmp::max(*w, utils::count(cell));
+            }
+        }
+
+        for (i, cells) in iter::once(&header).chain(&rows).enumerate() {
+            widgets::Text::<_, align::x::Left, align::y::Top>::new(
+                true,
+                format_row(cells, &widths),
+            )
+            .render(target, width, 1, x_off, y_off + i as u16);
+        }
+    }
+}
+
+impl HandleInput for Peers {
+    fn input(&mut self, k: Key, _: u16, _: u16) -> InputResult {
+        match k {
+            Key::Esc | Key::Char('q') => {
+                self.unsubscribe();
+                InputResult::Close
+            }
+            _ => InputResult::Key(k),
+        }
+    }
+}
+
+impl HandleRpc for Peers {
+    fn rpc(&mut self, msg: SMessage) -> bool {
+        match msg {
+            SMessage::UpdateResources { resources, .. } => {
+                let mut changed = false;
+                for upd in resources {
+                    match upd {
+                        SResourceUpdate::Resource(res) => {
+                            if let Resource::Peer(p) = res.into_owned() {
+                                if p.torrent_id != self.torrent_id {
+                                    continue;
+                                }
+                                if let Some(old) = self.peers.iter_mut().find(|o| o.id == p.id) {
+                                    *old = p;
+                                } else {
+                                    self.peers.push(p);
+                                }
+                                changed = true;
+                            }
+                        }
+                        SResourceUpdate::Rate {
+                            kind: ResourceKind::Peer,
+                            ..
+                        }
+                        | SResourceUpdate::PeerAvailability { .. } => {
+                            if let Some(p) = self.peers.iter_mut().find(|p| p.id == upd.id()) {
+                                p.update(upd);
+                                changed = true;
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                changed
+            }
+            SMessage::ResourcesRemoved { ids, .. } => {
+                let len = self.peers.len();
+                self.peers.retain(|p| !ids.contains(&p.id));
+                len != self.peers.len()
+            }
+            _ => false,
+        }
+    }
+}
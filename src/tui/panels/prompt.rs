@@ -0,0 +1,147 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use synapse_rpc::message::SMessage;
+use termion::event::Key;
+
+use crate::{
+    tui::{widgets, Component, HandleInput, HandleRpc, InputResult, Renderable},
+    utils::{align, color::ColorEscape},
+};
+
+/// A single line input, whose content is handed to a callback on `<ENTER>`
+// Returns why the input was rejected, if it was
+type Submit = Box<dyn FnMut(&str) -> Result<(), String> + Send + Sync>;
+
+pub struct Prompt {
+    label: String,
+    input: widgets::Input,
+    submit: Submit,
+    error: Option<String>,
+    // Offered on <TAB>, and the typed prefix and index of the last completion
+    completions: Vec<String>,
+    completion: Option<(String, usize)>,
+}
+
+impl Prompt {
+    pub fn new<F>(label: String, content: String, submit: F) -> Prompt
+    where
+        F: FnMut(&str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        Prompt {
+            label,
+            input: widgets::Input::from(content, None),
+            submit: Box::new(submit),
+            error: None,
+            completions: Vec::new(),
+            completion: None,
+        }
+    }
+
+    pub fn completions(mut self, mut completions: Vec<String>) -> Prompt {
+        completions.sort();
+        completions.dedup();
+        self.completions = completions;
+        self
+    }
+
+    // Replaces the input with the next completion of the typed prefix
+    fn complete(&mut self) {
+        let (prefix, idx) = match self.completion.take() {
+            Some((prefix, idx)) => (prefix, idx + 1),
+            None => (self.input.inner().to_owned(), 0),
+        };
+        let candidates: Vec<_> = self
+            .completions
+            .iter()
+            .filter(|c| c.starts_with(&*prefix))
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+
+        let idx = idx % candidates.len();
+        self.input = widgets::Input::from(candidates[idx].clone(), None);
+        // A unique match is final
+        if candidates.len() > 1 {
+            self.completion = Some((prefix, idx));
+        }
+    }
+}
+
+impl Component for Prompt {
+    fn typing(&self) -> bool {
+        true
+    }
+}
+
+impl Renderable for Prompt {
+    fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
+        widgets::Text::<_, align::x::Left, align::y::Top>::new(
+            true,
+            format!("{}: {}", self.label, self.input.format_active()),
+        )
+        .render(target, width, 1, x_off, y_off);
+
+        if let (Some(ref e), true) = (&self.error, height >= 2) {
+            widgets::Text::<_, align::x::Left, align::y::Top>::new(
+                true,
+                format!("{}{}{}", ColorEscape::error(), e, ColorEscape::reset()),
+            )
+            .render(target, width, 1, x_off, y_off + 1);
+        }
+    }
+}
+
+impl HandleInput for Prompt {
+    fn input(&mut self, k: Key, _: u16, _: u16) -> InputResult {
+        if k != Key::Char('\t') {
+            self.completion = None;
+        }
+        match k {
+            Key::Esc => return InputResult::Close,
+            Key::Char('\t') if !self.completions.is_empty() => self.complete(),
+            Key::Char('\n') => match (self.submit)(self.input.inner()) {
+                Ok(()) => return InputResult::Close,
+                Err(e) => self.error = Some(e),
+            },
+            Key::Home => self.input.home(),
+            Key::End => self.input.end(),
+            Key::Left => self.input.cursor_left(),
+            Key::Right => self.input.cursor_right(),
+            Key::Alt('b') => self.input.word_left(),
+            Key::Alt('f') => self.input.word_right(),
+            Key::Ctrl('w') => self.input.delete_word(),
+            Key::Ctrl('k') => self.input.kill_to_end(),
+            Key::Ctrl('u') => self.input.kill_to_start(),
+            Key::Backspace => self.input.backspace(),
+            Key::Delete => self.input.delete(),
+            Key::Char(c) => self.input.push(c),
+            _ => return InputResult::Key(k),
+        }
+        InputResult::Rerender
+    }
+
+    fn paste(&mut self, text: &str, _: u16, _: u16) -> InputResult {
+        self.input.push_str(text);
+        InputResult::Rerender
+    }
+}
+
+impl HandleRpc for Prompt {
+    fn rpc(&mut self, _: SMessage) -> bool {
+        false
+    }
+}
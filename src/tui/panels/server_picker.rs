@@ -0,0 +1,125 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use synapse_rpc::message::SMessage;
+use termion::{cursor, event::Key};
+
+use std::{io::Write, time::Instant};
+
+use super::{login, Login};
+use crate::{
+    config::CONFIG,
+    keymap::Action,
+    rpc::Auth,
+    tui::{Component, HandleInput, HandleRpc, InputResult, Renderable},
+    utils::{
+        align::{self, x::Align},
+        color::ColorEscape,
+    },
+};
+
+/// Lists the instances of the `[[servers]]` config, so that one can be logged in to
+pub struct ServerPicker {
+    selected: usize,
+    // When the pending connection attempt was started, if there is one
+    connecting: Option<Instant>,
+}
+
+impl ServerPicker {
+    pub fn new() -> ServerPicker {
+        ServerPicker {
+            selected: 0,
+            connecting: None,
+        }
+    }
+}
+
+impl Component for ServerPicker {
+    fn connecting(&mut self, since: Option<Instant>) {
+        self.connecting = since;
+    }
+}
+
+impl HandleRpc for ServerPicker {
+    fn rpc(&mut self, _: SMessage) -> bool {
+        false
+    }
+}
+
+impl Renderable for ServerPicker {
+    fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, _: u16, _: u16) {
+        let mut lines = vec![
+            "Welcome to axon, the synapse TUI".to_owned(),
+            "Select a synapse instance, or <ESC> to enter another one:".to_owned(),
+        ];
+        for (i, srv) in CONFIG.servers.iter().enumerate() {
+            if i == self.selected {
+                lines.push(format!(
+                    "{}> {} ({}){}",
+                    ColorEscape::selection(),
+                    srv.name,
+                    srv.url,
+                    ColorEscape::reset()
+                ));
+            } else {
+                lines.push(format!("  {} ({})", srv.name, srv.url));
+            }
+        }
+        if let Some(status) = login::connecting_status(self.connecting) {
+            lines.push(status);
+        }
+        let lines: Vec<_> = lines.iter().map(|l| &**l).collect();
+
+        write!(
+            target,
+            "{}",
+            cursor::Goto(
+                match align::x::CenterLongestLeft::align_offset(&lines, width) {
+                    align::x::Alignment::Single(x) => x,
+                },
+                height / 3
+            )
+        )
+        .unwrap();
+        align::x::Left::align(target, &lines);
+    }
+}
+
+impl HandleInput for ServerPicker {
+    fn input(&mut self, k: Key, _: u16, _: u16) -> InputResult {
+        match k {
+            Key::Esc => return InputResult::ReplaceWith(Box::new(Login::new())),
+            k if k == Key::Up || CONFIG.keys.is(Action::Up, k) => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            k if k == Key::Down || CONFIG.keys.is(Action::Down, k) => {
+                if self.selected + 1 < CONFIG.servers.len() {
+                    self.selected += 1;
+                }
+            }
+            k if CONFIG.keys.is(Action::Connect, k) => {
+                let srv = &CONFIG.servers[self.selected];
+                return match Auth::from_config(srv.pass.as_ref(), srv.token.as_ref()) {
+                    Some(auth) => InputResult::ConnectWith(srv.url.clone(), auth),
+                    None => {
+                        InputResult::ReplaceWith(Box::new(Login::with_server(Some(&srv.url), None)))
+                    }
+                };
+            }
+            _ => return InputResult::Key(k),
+        }
+        InputResult::Rerender
+    }
+}
@@ -0,0 +1,606 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use chrono::{DateTime, TimeZone, Utc};
+use parking_lot::Mutex;
+use serde_json::json;
+use synapse_rpc::{
+    message::{CMessage, Error, SMessage, Version},
+    resource::{Peer, Resource, SResourceUpdate, Server, Status, Torrent, Tracker},
+};
+use termion::event::Key;
+use url::Url;
+
+use std::{
+    borrow::Cow,
+    env, fs,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::{
+    tracker_host, tracker_tag, Confirm, Main, Peers, Prompt, ServerPicker, TorrentDetails,
+};
+use crate::{
+    keymap::{Action, KeyMap},
+    rpc::{MessageSink, WsSink},
+    tui::{screen::Screen, Component, HandleInput, HandleRpc, InputResult, Renderable},
+    utils::{color::ColorEscape, fmt},
+};
+
+const WIDTH: u16 = 80;
+const HEIGHT: u16 = 24;
+
+// Records the messages instead of sending them
+#[derive(Default)]
+struct Recorder(Mutex<Vec<CMessage>>);
+
+impl MessageSink for Recorder {
+    fn send(&self, msg: CMessage) {
+        self.0.lock().push(msg);
+    }
+}
+
+// A while before the time relative dates are formatted against
+fn date() -> DateTime<Utc> {
+    fmt::now() - chrono::Duration::seconds(3723)
+}
+
+fn server() -> Server {
+    Server {
+        id: "server".to_owned(),
+        rate_up: 1024,
+        rate_down: 4 * 1024 * 1024,
+        transferred_up: 3 * 1024 * 1024 * 1024,
+        transferred_down: 5 * 1024 * 1024 * 1024,
+        ses_transferred_up: 1024 * 1024,
+        ses_transferred_down: 2 * 1024 * 1024,
+        free_space: 100 * 1024 * 1024 * 1024,
+        started: date(),
+        ..Default::default()
+    }
+}
+
+fn torrent(id: &str, name: &str, status: Status, progress: f32) -> Torrent {
+    Torrent {
+        id: id.to_owned(),
+        name: Some(name.to_owned()),
+        path: "/srv/torrents".to_owned(),
+        created: date(),
+        modified: date(),
+        status,
+        progress,
+        size: Some(700 * 1024 * 1024),
+        transferred_down: (progress * 700. * 1024. * 1024.) as u64,
+        peers: 3,
+        trackers: 1,
+        pieces: Some(1400),
+        piece_size: Some(512 * 1024),
+        files: Some(2),
+        user_data: json!({}),
+        ..Default::default()
+    }
+}
+
+fn tracker(id: &str, torrent_id: &str, url: &str) -> Tracker {
+    Tracker {
+        id: id.to_owned(),
+        torrent_id: torrent_id.to_owned(),
+        url: Url::parse(url).unwrap(),
+        last_report: date(),
+        error: None,
+        user_data: json!({}),
+    }
+}
+
+// A main panel that received a fixed set of resources
+fn main_panel() -> Main {
+    let sink: WsSink = Arc::new(Recorder::default());
+    let mut main = Main::new(&sink, HEIGHT);
+    main.rpc(SMessage::RpcVersion(Version { major: 0, minor: 1 }));
+    let resources = vec![
+        Resource::Server(server()),
+        Resource::Torrent(torrent("t1", "debian-10.iso", Status::Seeding, 1.)),
+        Resource::Torrent(torrent("t2", "Arch Linux", Status::Leeching, 0.42)),
+        Resource::Torrent(torrent("t3", "ubuntu-19.04.iso", Status::Paused, 0.)),
+        Resource::Tracker(tracker("tr1", "t1", "udp://tracker.example.org:1337")),
+        Resource::Tracker(tracker("tr2", "t2", "http://announce.example.com/")),
+    ];
+    main.rpc(SMessage::UpdateResources {
+        serial: None,
+        resources: resources
+            .into_iter()
+            .map(|r| SResourceUpdate::Resource(Cow::Owned(r)))
+            .collect(),
+    });
+    main
+}
+
+// Renders the component, and reduces the output to the text it draws, without escape codes
+fn render<R: Renderable + ?Sized>(r: &mut R, width: u16, height: u16) -> String {
+    let mut frame = Vec::new();
+    r.render(&mut frame, width, height, 1, 1);
+    let mut screen = Screen::new();
+    screen.update(&frame, width, height, &mut Vec::new());
+    screen.text()
+}
+
+// Compares the text to the stored snapshot, or stores it if `UPDATE_SNAPSHOTS` is set
+fn assert_snapshot(name: &str, text: &str) {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "src/tui/panels/snapshots",
+        &format!("{}.txt", name),
+    ]
+    .iter()
+    .collect();
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, text).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read snapshot {}: {}, run with UPDATE_SNAPSHOTS=1 to create it",
+            path.display(),
+            e
+        )
+    });
+    assert!(
+        expected == text,
+        "Rendering differs from snapshot {}:\n{}\n\nExpected:\n{}",
+        path.display(),
+        text,
+        expected
+    );
+}
+
+#[test]
+fn torrent_list() {
+    let mut main = main_panel();
+    assert_snapshot("torrent_list", &render(&mut main, WIDTH, HEIGHT));
+}
+
+#[test]
+fn torrent_list_with_stats() {
+    // The stats columns are only shown if they leave enough space for the names
+    let mut main = main_panel();
+    assert_snapshot("torrent_list_with_stats", &render(&mut main, 140, HEIGHT));
+}
+
+#[test]
+fn torrent_list_header() {
+    let mut main = main_panel();
+    main.torrent_header = true;
+    assert_snapshot("torrent_list_header", &render(&mut main, 140, HEIGHT));
+}
+
+#[test]
+fn trackers_and_details() {
+    let mut main = main_panel();
+    main.input(Key::Char('t'), WIDTH, HEIGHT);
+    main.input(Key::Char('j'), WIDTH, HEIGHT);
+    main.input(Key::Char('d'), WIDTH, HEIGHT);
+    assert_snapshot("trackers_and_details", &render(&mut main, WIDTH, HEIGHT));
+}
+
+#[test]
+fn details_trackers() {
+    let mut main = main_panel();
+    let mut t4 = torrent("t4", "fedora.iso", Status::Leeching, 0.);
+    t4.tracker_urls = vec![
+        "pending.example.net".to_owned(),
+        "tracker.example.org".to_owned(),
+    ];
+    main.rpc(SMessage::UpdateResources {
+        serial: None,
+        resources: vec![
+            SResourceUpdate::Resource(Cow::Owned(Resource::Torrent(t4.clone()))),
+            SResourceUpdate::Resource(Cow::Owned(Resource::Tracker(tracker(
+                "tr3",
+                "t4",
+                "udp://tracker.example.org:1337",
+            )))),
+        ],
+    });
+
+    // The tracker whose resource did not arrive yet is kept in the torrent's order
+    let trackers = main.torrent_trackers(&t4);
+    assert_eq!(trackers.len(), 2);
+    assert_eq!(trackers[0].host, "pending.example.net");
+    assert!(trackers[0].status.is_none());
+    assert_eq!(
+        trackers[1].status.as_ref().unwrap().0.as_str(),
+        "udp://tracker.example.org:1337/"
+    );
+
+    let mut details = TorrentDetails::new(t4, false);
+    details.set_trackers(trackers);
+    let text = render(&mut details, WIDTH, 7);
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines[5], "1. pending.example.net   waiting for the server");
+    assert_eq!(
+        lines[6],
+        "2. udp://tracker.example.org:1337/   announced 01:02:03 ago"
+    );
+}
+
+#[test]
+fn confirm() {
+    let main = main_panel();
+    let mut confirm = Confirm::new(
+        "Raise the priority of Arch Linux?".to_owned(),
+        Action::PriorityUp,
+        Box::new(main.clone()),
+    );
+    assert_snapshot("confirm", &render(&mut confirm, WIDTH, HEIGHT));
+    match confirm.input(Key::Char('n'), WIDTH, HEIGHT) {
+        InputResult::ReplaceWith(_) => {}
+        _ => panic!("the confirmation was not closed"),
+    }
+
+    let mut main = main;
+    main.input_confirmed(Action::PriorityUp, WIDTH, HEIGHT);
+    assert_eq!(main.priority_pending, Some(("t2".to_owned(), 4)));
+
+    // Quitting is left to the view
+    let mut quit = Confirm::new("Quit?".to_owned(), Action::Quit, Box::new(main));
+    match quit.input(Key::Char('y'), WIDTH, HEIGHT) {
+        InputResult::Quit => {}
+        _ => panic!("quitting was not confirmed"),
+    }
+}
+
+#[test]
+fn priority_bounds() {
+    let mut main = main_panel();
+    main.torrents.2[0].priority = 5;
+    main.input_confirmed(Action::PriorityUp, WIDTH, HEIGHT);
+    assert_eq!(main.priority_pending, None);
+
+    main.input_confirmed(Action::PriorityDown, WIDTH, HEIGHT);
+    assert_eq!(main.priority_pending, Some(("t2".to_owned(), 4)));
+    main.rpc(SMessage::InvalidRequest(Error {
+        serial: None,
+        reason: "Priority out of range".to_owned(),
+    }));
+    assert_eq!(main.priority_pending, None);
+}
+
+#[test]
+fn connecting_status() {
+    let mut picker = ServerPicker::new();
+    assert!(!render(&mut picker, WIDTH, HEIGHT).contains("Connecting"));
+    picker.connecting(Some(Instant::now()));
+    assert!(render(&mut picker, WIDTH, HEIGHT).contains("Connecting… 0s"));
+    // The attempt failed
+    picker.connecting(None);
+    assert!(!render(&mut picker, WIDTH, HEIGHT).contains("Connecting"));
+}
+
+#[test]
+fn bindings_while_typing() {
+    let keys = KeyMap::default();
+    let mut main = main_panel();
+    assert!(!main.typing());
+    main.input(keys.get(Action::Filter), WIDTH, HEIGHT);
+    assert!(main.typing());
+    // Printable keys are typed into the filter, others are still bindings
+    assert!(!keys.is_unless_typing(Action::Filter, Key::Char('f'), main.typing()));
+    assert!(keys.is_unless_typing(Action::Quit, Key::Ctrl('q'), main.typing()));
+}
+
+#[test]
+fn peer_columns() {
+    let sink: WsSink = Arc::new(Recorder::default());
+    let mut peers = Peers::new(&sink, "t1".to_owned());
+    let peer = |id: &str, ip: &str, client_id: &str, rate_down| {
+        SResourceUpdate::Resource(Cow::Owned(Resource::Peer(Peer {
+            id: id.to_owned(),
+            torrent_id: "t1".to_owned(),
+            client_id: client_id.to_owned(),
+            ip: ip.to_owned(),
+            rate_down,
+            availability: 0.5,
+            ..Default::default()
+        })))
+    };
+    peers.rpc(SMessage::UpdateResources {
+        serial: None,
+        resources: vec![
+            peer("p1", "10.0.0.1:51413", "-TR2940-", 2048),
+            peer("p2", "[2001:db8::1]:6881", "-qB4150-", 1024),
+        ],
+    });
+    let text = render(&mut peers, WIDTH, 3);
+    let lines: Vec<_> = text.lines().map(|l| l.trim_end()).collect();
+    // The header is padded like the rows
+    assert_eq!(lines[0].find("Client"), lines[1].find("-TR2940-"));
+    assert_eq!(lines[1].find("-TR2940-"), lines[2].find("-qB4150-"));
+    assert!(lines
+        .iter()
+        .all(|l| l.chars().count() == lines[0].chars().count()));
+}
+
+#[test]
+fn prompt_completion() {
+    let labels = vec!["work".to_owned(), "games".to_owned(), "work".to_owned()];
+    let mut prompt = Prompt::new("Label".to_owned(), String::new(), |_| Ok(())).completions(labels);
+    let complete = |prompt: &mut Prompt, k| {
+        prompt.input(k, WIDTH, 1);
+        render(prompt, WIDTH, 1)
+    };
+    assert_eq!(complete(&mut prompt, Key::Char('\t')), "Label: games\n");
+    assert_eq!(complete(&mut prompt, Key::Char('\t')), "Label: work\n");
+    assert_eq!(complete(&mut prompt, Key::Char('\t')), "Label: games\n");
+
+    // Typing ends the cycle, the next completion is of the new input
+    complete(&mut prompt, Key::Backspace);
+    assert_eq!(complete(&mut prompt, Key::Char('\t')), "Label: games\n");
+}
+
+#[test]
+fn tracker_sort() {
+    let mut main = main_panel();
+    main.rpc(SMessage::UpdateResources {
+        serial: None,
+        resources: vec![SResourceUpdate::Resource(Cow::Owned(Resource::Tracker(
+            tracker("tr3", "t3", "udp://tracker.example.org:1337"),
+        )))],
+    });
+    main.input(Key::Char('t'), WIDTH, HEIGHT);
+    main.input(Key::Char('H'), WIDTH, HEIGHT);
+    assert_eq!(main.tracker_order, vec![0, 1]);
+
+    // The highlight stays on announce.example.com, which now has the fewest torrents
+    main.input(Key::Char('o'), WIDTH, HEIGHT);
+    assert_eq!(main.tracker_order, vec![1, 0]);
+    assert_eq!(main.tracker_sel.1, 1);
+
+    // Without errors, the trackers are ordered by URL
+    main.input(Key::Char('o'), WIDTH, HEIGHT);
+    assert_eq!(main.tracker_order, vec![0, 1]);
+    assert_eq!(main.tracker_sel.1, 0);
+}
+
+#[test]
+fn tracker_tags() {
+    let tag = |url| tracker_tag(&tracker("tr", "t", url)).to_owned();
+    assert_eq!(tag("udp://tracker.example.org:1337"), "tracker");
+    assert_eq!(tag("http://localhost/announce"), "localhost");
+    assert_eq!(tag("http://192.168.1.2:6969/announce"), "192.168.1.2");
+}
+
+#[test]
+fn tracker_errors_show_their_own_announce() {
+    let mut main = main_panel();
+    // Grouped with the tracker of debian-10.iso, which announced just now
+    let mut tr = tracker("tr3", "t2", "udp://tracker.example.org:1337");
+    tr.last_report = Utc.timestamp_opt(0, 0).unwrap();
+    tr.error = Some("unregistered torrent".to_owned());
+    main.rpc(SMessage::UpdateResources {
+        serial: None,
+        resources: vec![SResourceUpdate::Resource(Cow::Owned(Resource::Tracker(tr)))],
+    });
+    main.torrents.2[0].tracker_urls = vec!["tracker.example.org".to_owned()];
+
+    let mut errors = match main.input(Key::Char('e'), WIDTH, HEIGHT) {
+        InputResult::ReplaceWith(errors) => errors,
+        _ => panic!("the errors were not shown"),
+    };
+    let text = render(&mut *errors, WIDTH, HEIGHT);
+    assert!(text.contains("tracker.example.org: unregistered torrent"));
+    assert!(text.contains("last announce: never"));
+}
+
+#[test]
+fn tracker_hosts() {
+    let host = |url| tracker_host(&tracker("tr", "t", url)).to_owned();
+    assert_eq!(
+        host("udp://tracker.example.org:1337"),
+        "tracker.example.org"
+    );
+    // Without a host the whole URL is shown, instead of panicking
+    assert_eq!(host("udp:"), "udp:");
+    assert_eq!(host("urn:btih:announce"), "urn:btih:announce");
+    assert_eq!(tracker_tag(&tracker("tr", "t", "udp:")), "udp:");
+}
+
+#[test]
+fn aggregate_row() {
+    let mut main = main_panel();
+    main.torrent_header = true;
+    main.aggregate_row = true;
+    assert_snapshot("aggregate_row", &render(&mut main, 140, HEIGHT));
+}
+
+#[test]
+fn zebra() {
+    let mut main = main_panel();
+    let plain = render(&mut main, 140, HEIGHT);
+    main.zebra = true;
+    assert_eq!(render(&mut main, 140, HEIGHT), plain);
+
+    let mut frame = Vec::new();
+    main.render(&mut frame, 140, HEIGHT, 1, 1);
+    let frame = String::from_utf8(frame).unwrap();
+    // Only the second row is shaded, by the fill, the name and the stats
+    assert_eq!(frame.matches(ColorEscape::zebra().inner()).count(), 3);
+}
+
+#[test]
+fn selection_spans_row() {
+    let mut main = main_panel();
+    main.torrents.2[0].error = Some("tracker unreachable".to_owned());
+
+    let mut frame = Vec::new();
+    main.render(&mut frame, 140, HEIGHT, 1, 1);
+    let frame = String::from_utf8(frame).unwrap();
+    // The gap between the name and the stats is filled too
+    assert_eq!(frame.matches(ColorEscape::error_bg().inner()).count(), 3);
+
+    main.torrents.2[0].error = None;
+    let mut frame = Vec::new();
+    main.render(&mut frame, 140, HEIGHT, 1, 1);
+    let frame = String::from_utf8(frame).unwrap();
+    assert_eq!(
+        frame.matches(ColorEscape::selection_bg().inner()).count(),
+        3
+    );
+}
+
+#[test]
+fn status_counts_make_room_for_server_stats() {
+    let mut main = main_panel();
+    // The counts are dropped before the server stats are cut off
+    let footer = render(&mut main, 140, HEIGHT);
+    assert!(!footer.contains("▸"));
+
+    let footer = render(&mut main, 240, HEIGHT);
+    let line = footer
+        .lines()
+        .rev()
+        .find(|l| l.starts_with("Server"))
+        .unwrap();
+    assert!(line.contains("Lifetime: 3.00 GiB↑ 5.00 GiB↓ → 0.60"));
+    assert!(line.contains("▸ 1 seed  1 leech  0 err"));
+}
+
+#[test]
+fn status_counts() {
+    let mut main = main_panel();
+    assert_eq!(main.status_counts(), "▸ 1 seed  1 leech  0 err");
+
+    main.rpc(SMessage::UpdateResources {
+        serial: None,
+        resources: vec![SResourceUpdate::Resource(Cow::Owned(Resource::Torrent(
+            torrent("t4", "fedora.iso", Status::Error, 0.),
+        )))],
+    });
+    assert_eq!(
+        main.status_counts(),
+        format!(
+            "▸ 1 seed  1 leech  {}1 err{}",
+            ColorEscape::error(),
+            ColorEscape::reset()
+        )
+    );
+}
+
+#[test]
+fn added_highlight() {
+    let mut main = main_panel();
+    main.added.clear();
+    let add = |main: &mut Main, serial, id| {
+        main.rpc(SMessage::UpdateResources {
+            serial,
+            resources: vec![SResourceUpdate::Resource(Cow::Owned(Resource::Torrent(
+                torrent(id, id, Status::Leeching, 0.),
+            )))],
+        });
+    };
+    // Torrents sent in reply to a subscription existed before
+    add(&mut main, Some(1), "t4");
+    add(&mut main, None, "t5");
+    assert!(!main.added.contains_key("t4"));
+    assert!(main.added.contains_key("t5"));
+
+    assert!(!main.expire());
+    *main.added.get_mut("t5").unwrap() -= Duration::from_secs(10);
+    assert!(main.expire());
+    assert!(main.added.is_empty());
+
+    // Torrents that stopped and then started matching the filter again are not new
+    main.rpc(SMessage::ResourcesRemoved {
+        serial: 2,
+        ids: vec!["t4".to_owned(), "t5".to_owned()],
+    });
+    add(&mut main, None, "t4");
+    add(&mut main, None, "t5");
+    assert!(main.added.is_empty());
+}
+
+#[test]
+fn filter_count() {
+    let mut main = main_panel();
+    main.input(Key::Char('f'), WIDTH, HEIGHT);
+    for c in "iso".chars() {
+        main.input(Key::Char(c), WIDTH, HEIGHT);
+    }
+    assert_snapshot("filter_count", &render(&mut main, WIDTH, HEIGHT));
+}
+
+#[test]
+fn search() {
+    let mut main = main_panel();
+    // Enter jumps to the first match even if the selected torrent matches too
+    main.input(Key::Down, WIDTH, HEIGHT);
+    main.input(Key::Char('/'), WIDTH, HEIGHT);
+    for c in "ISO".chars() {
+        main.input(Key::Char(c), WIDTH, HEIGHT);
+    }
+    assert_snapshot("search", &render(&mut main, WIDTH, HEIGHT));
+
+    main.input(Key::Char('\n'), WIDTH, HEIGHT);
+    assert_eq!(main.torrents.1, 1);
+    main.input(Key::Char('n'), WIDTH, HEIGHT);
+    assert_eq!(main.torrents.1, 2);
+    main.input(Key::Char('n'), WIDTH, HEIGHT);
+    assert_eq!(main.torrents.1, 1);
+}
+
+#[test]
+fn removing_torrents_keeps_the_selection() {
+    let remove = |main: &mut Main, ids: &[&str]| {
+        main.rpc(SMessage::ResourcesRemoved {
+            serial: 1,
+            ids: ids.iter().map(|&id| id.to_owned()).collect(),
+        });
+    };
+    let selected = |main: &Main| main.torrents.2[main.torrents.1].id.clone();
+
+    // The list is Arch Linux (t2), debian-10.iso (t1), ubuntu-19.04.iso (t3)
+    let mut main = main_panel();
+    main.input(Key::Down, WIDTH, HEIGHT);
+    main.input(Key::Down, WIDTH, HEIGHT);
+    // A torrent above the selection
+    remove(&mut main, &["t2"]);
+    assert_eq!((main.torrents.0, main.torrents.1), (0, 1));
+    assert_eq!(selected(&main), "t3");
+    // The selected torrent, which is the last one
+    remove(&mut main, &["t3"]);
+    assert_eq!((main.torrents.0, main.torrents.1), (0, 0));
+    assert_eq!(selected(&main), "t1");
+
+    // The selected torrent moves the selection to the next one
+    let mut main = main_panel();
+    main.input(Key::Down, WIDTH, HEIGHT);
+    remove(&mut main, &["t1"]);
+    assert_eq!((main.torrents.0, main.torrents.1), (0, 1));
+    assert_eq!(selected(&main), "t3");
+
+    // All of them
+    remove(&mut main, &["t2", "t3"]);
+    assert_eq!((main.torrents.0, main.torrents.1), (0, 0));
+    assert!(main.torrents.2.is_empty());
+    render(&mut main, WIDTH, HEIGHT);
+}
+
+#[test]
+fn totals() {
+    let mut main = main_panel();
+    main.input(Key::Char('T'), WIDTH, HEIGHT);
+    assert_snapshot("totals", &render(&mut main, WIDTH, HEIGHT));
+}
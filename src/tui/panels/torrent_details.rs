@@ -13,24 +13,47 @@
 // You should have received a copy of the GNU General Public License
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
-use synapse_rpc::resource::Torrent;
+use chrono::{DateTime, Utc};
+use synapse_rpc::resource::{Strategy, Torrent};
+use url::Url;
 
 use crate::{
     tui::{widgets, Renderable},
     utils::{
         align,
+        color::ColorEscape,
         fmt::{self, FormatSize},
     },
 };
 
+// The width of the progress gauge, which is only shown if the line is at least GAUGE_MIN_LINE wide
+const GAUGE_WIDTH: u16 = 10;
+const GAUGE_MIN_LINE: u16 = 100;
+
+/// A tracker of the torrent
+#[derive(Clone)]
+pub struct DetailsTracker {
+    pub host: String,
+    // The URL, last announce, and error of the tracker, once its resource arrived
+    pub status: Option<(Url, DateTime<Utc>, Option<String>)>,
+}
+
 #[derive(Clone)]
 pub struct TorrentDetails {
     torr: Torrent,
+    // Listed below the stats, if the pane is tall enough
+    trackers: Vec<DetailsTracker>,
+    // Show the dates of the torrent instead of how long ago they were
+    absolute_times: bool,
 }
 
 impl TorrentDetails {
-    pub fn new(torr: Torrent) -> TorrentDetails {
-        TorrentDetails { torr }
+    pub fn new(torr: Torrent, absolute_times: bool) -> TorrentDetails {
+        TorrentDetails {
+            torr,
+            trackers: Vec::new(),
+            absolute_times,
+        }
     }
     pub fn inner(&self) -> &Torrent {
         &self.torr
@@ -38,31 +61,66 @@ impl TorrentDetails {
     pub fn inner_mut(&mut self) -> &mut Torrent {
         &mut self.torr
     }
+    pub fn set_trackers(&mut self, trackers: Vec<DetailsTracker>) {
+        self.trackers = trackers;
+    }
+    pub fn set_absolute_times(&mut self, absolute_times: bool) {
+        self.absolute_times = absolute_times;
+    }
+
+    fn fmt_time(&self, date: DateTime<Utc>) -> String {
+        if self.absolute_times {
+            fmt::fmt_datetime_local(date)
+        } else {
+            format!("{} ago", fmt::date_diff_now(date))
+        }
+    }
+
+    fn fmt_percent(val: f32) -> String {
+        if val.is_nan() {
+            "?".to_owned()
+        } else {
+            format!("{}%", (val * 100.).round())
+        }
+    }
+
+    // The availability is shown dimmed behind the progress, so that pieces missing from the swarm
+    // stand out
+    fn fmt_progress(&self, gauge: bool) -> String {
+        let percent = TorrentDetails::fmt_percent(self.torr.progress);
+        if gauge {
+            let bar = widgets::Gauge::new(self.torr.progress)
+                .secondary(self.torr.availability)
+                .bar(GAUGE_WIDTH);
+            format!("[{}] {}", bar, percent)
+        } else {
+            percent
+        }
+    }
 }
 
 impl Renderable for TorrentDetails {
     fn name(&self) -> String {
-        self.torr
-            .name
-            .as_ref()
-            .unwrap_or_else(|| &self.torr.path)
-            .clone()
+        fmt::torrent_name(&self.torr).to_owned()
     }
     fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
         if height >= 1 {
             widgets::Text::<_, align::x::Left, align::y::Top>::new(
                 true,
                 format!(
-                    "{}, {}   Picker: {:?}   Created: {} ago   Modified: {} ago",
+                    "{}, {}   Picker: {}   Created: {}   Modified: {}",
                     if self.torr.private {
                         "Private"
                     } else {
                         "Public"
                     },
                     self.torr.status.as_str(),
-                    self.torr.strategy,
-                    fmt::date_diff_now(self.torr.created),
-                    fmt::date_diff_now(self.torr.modified),
+                    match self.torr.strategy {
+                        Strategy::Rarest => "rarest first",
+                        Strategy::Sequential => "sequential",
+                    },
+                    self.fmt_time(self.torr.created),
+                    self.fmt_time(self.torr.modified),
                 ),
             )
             .render(target, width, 1, x_off, y_off);
@@ -72,24 +130,20 @@ impl Renderable for TorrentDetails {
             widgets::Text::<_, align::x::Left, align::y::Top>::new(
                 true,
                 format!(
-                    "Rates: {}[{}]↑ {}[{}]↓   Lifetime: {}↑ {}↓ → {:.2}",
-                    self.torr.rate_up.fmt_size(),
+                    "Rates: {}[{}]↑ {}[{}]↓   Lifetime: {}↑ {}↓ → {}",
+                    self.torr.rate_up.fmt_rate(),
                     self.torr
                         .throttle_up
-                        .map(|t| if t == -1 { "∞".into() } else { t.fmt_size() })
+                        .map(|t| if t == -1 { "∞".into() } else { t.fmt_rate() })
                         .unwrap_or_else(|| "*".into()),
-                    self.torr.rate_down.fmt_size_align(),
+                    self.torr.rate_down.fmt_rate(),
                     self.torr
                         .throttle_down
-                        .map(|t| if t == -1 { "∞".into() } else { t.fmt_size() })
+                        .map(|t| if t == -1 { "∞".into() } else { t.fmt_rate() })
                         .unwrap_or_else(|| "*".into()),
                     self.torr.transferred_up.fmt_size(),
                     self.torr.transferred_down.fmt_size(),
-                    if self.torr.transferred_down == 0 {
-                        1.
-                    } else {
-                        self.torr.transferred_up as f32 / self.torr.transferred_down as f32
-                    },
+                    fmt::fmt_ratio(self.torr.transferred_up, self.torr.transferred_down),
                 ),
             )
             .render(target, width, 1, x_off, y_off + 1);
@@ -99,13 +153,14 @@ impl Renderable for TorrentDetails {
             widgets::Text::<_, align::x::Left, align::y::Top>::new(
                 true,
                 format!(
-                    "Size: {}   Progress: {}%   Availability: {}%   Priority: {}",
+                    "Size: {}   Progress: {}   ETA: {}   Availability: {}   Priority: {}",
                     self.torr
                         .size
                         .map(|p| p.fmt_size())
                         .unwrap_or_else(|| "?".into()),
-                    (self.torr.progress * 100.).round(),
-                    (self.torr.availability * 100.).round(),
+                    self.fmt_progress(width >= GAUGE_MIN_LINE),
+                    fmt::torrent_eta(&self.torr),
+                    TorrentDetails::fmt_percent(self.torr.availability),
                     self.torr.priority,
                 ),
             )
@@ -143,5 +198,34 @@ impl Renderable for TorrentDetails {
             )
             .render(target, width, 1, x_off, y_off + 4);
         }
+
+        let lines = height.saturating_sub(5) as usize;
+        for (i, tr) in self.trackers.iter().enumerate().take(lines) {
+            let line = if i + 1 == lines && self.trackers.len() > lines {
+                format!("… {} more trackers", self.trackers.len() - i)
+            } else {
+                match tr.status {
+                    None => format!("{}. {}   waiting for the server", i + 1, tr.host),
+                    Some((ref url, _, Some(ref e))) => format!(
+                        "{}. {}   {}{}{}",
+                        i + 1,
+                        url,
+                        ColorEscape::error(),
+                        e,
+                        ColorEscape::reset()
+                    ),
+                    Some((ref url, last, None)) => {
+                        format!("{}. {}   announced {}", i + 1, url, self.fmt_time(last))
+                    }
+                }
+            };
+            widgets::Text::<_, align::x::Left, align::y::Top>::new(true, line).render(
+                target,
+                width,
+                1,
+                x_off,
+                y_off + 5 + i as u16,
+            );
+        }
     }
 }
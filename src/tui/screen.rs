@@ -0,0 +1,325 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use termion::{clear, cursor};
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::{cmp, io::Write, mem};
+
+use crate::utils;
+
+// The SGR state a cell is drawn with, kept as the parameters that set it
+#[derive(Clone, Default, PartialEq)]
+struct Style {
+    // Bit n is set if the attribute with SGR code n (bold, underline, …) is active
+    attrs: u16,
+    fg: Option<String>,
+    bg: Option<String>,
+}
+
+impl Style {
+    fn apply(&mut self, params: &str) {
+        let mut ps = params.split(';').map(|p| p.parse::<u16>().unwrap_or(0));
+        while let Some(p) = ps.next() {
+            match p {
+                0 => *self = Style::default(),
+                1..=9 => self.attrs |= 1 << p,
+                // termion's NoBold
+                21 => self.attrs &= !(1 << 1),
+                22 => self.attrs &= !(1 << 1 | 1 << 2),
+                23..=29 => self.attrs &= !(1 << (p - 20)),
+                30..=37 | 90..=97 => self.fg = Some(p.to_string()),
+                40..=47 | 100..=107 => self.bg = Some(p.to_string()),
+                38 | 48 => {
+                    // Either a palette index (5;n) or a truecolor code (2;r;g;b)
+                    let ext: Vec<_> = match ps.next() {
+                        Some(5) => ps.by_ref().take(1).collect(),
+                        Some(2) => ps.by_ref().take(3).collect(),
+                        _ => continue,
+                    };
+                    let code = ext.iter().fold(
+                        format!("{};{}", p, if ext.len() == 1 { 5 } else { 2 }),
+                        |acc, c| format!("{};{}", acc, c),
+                    );
+                    if p == 38 {
+                        self.fg = Some(code);
+                    } else {
+                        self.bg = Some(code);
+                    }
+                }
+                39 => self.fg = None,
+                49 => self.bg = None,
+                _ => {}
+            }
+        }
+    }
+
+    fn write(&self, target: &mut Vec<u8>) {
+        write!(target, "\x1B[0").unwrap();
+        for n in 1..10 {
+            if self.attrs & (1 << n) != 0 {
+                write!(target, ";{}", n).unwrap();
+            }
+        }
+        if let Some(ref fg) = self.fg {
+            write!(target, ";{}", fg).unwrap();
+        }
+        if let Some(ref bg) = self.bg {
+            write!(target, ";{}", bg).unwrap();
+        }
+        write!(target, "m").unwrap();
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct Cell {
+    // Empty if covered by the wide grapheme to the left
+    g: String,
+    style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Cell {
+        Cell {
+            g: " ".to_owned(),
+            style: Style::default(),
+        }
+    }
+}
+
+/// A virtual screen that rendered frames are applied to, so that only the cells that changed
+/// since the last frame are written to the terminal
+pub struct Screen {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+    prev: Vec<Cell>,
+    // Whether the terminal content is unknown, e.g. after a resize
+    invalid: bool,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen {
+            width: 0,
+            height: 0,
+            cells: Vec::new(),
+            prev: Vec::new(),
+            invalid: true,
+        }
+    }
+
+    /// Forces the next update to redraw everything
+    pub fn invalidate(&mut self) {
+        self.invalid = true;
+    }
+
+    /// Interprets the rendered frame and writes the changes to the last frame to target
+    pub fn update(&mut self, frame: &[u8], width: u16, height: u16, target: &mut Vec<u8>) {
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.invalid = true;
+        }
+        let len = width as usize * height as usize;
+        self.cells.clear();
+        self.cells.resize(len, Cell::default());
+        self.apply(&String::from_utf8_lossy(frame));
+
+        if self.invalid {
+            write!(target, "{}", clear::All).unwrap();
+            self.prev.clear();
+            self.prev.resize(len, Cell::default());
+            self.invalid = false;
+        }
+        self.diff(target);
+        mem::swap(&mut self.cells, &mut self.prev);
+    }
+
+    /// The text of the last frame, with trailing whitespace removed from each line
+    #[cfg(test)]
+    pub fn text(&self) -> String {
+        let width = cmp::max(self.width as usize, 1);
+        let mut text = String::new();
+        for row in self.prev.chunks(width) {
+            let line: String = row.iter().map(|c| &*c.g).collect();
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    fn apply(&mut self, frame: &str) {
+        let (mut x, mut y) = (0usize, 0usize);
+        let mut style = Style::default();
+        let mut rest = frame;
+
+        while !rest.is_empty() {
+            let text_end = rest.find('\x1B').unwrap_or(rest.len());
+            for g in rest[..text_end].graphemes(true) {
+                match g {
+                    "\n" => y += 1,
+                    "\r" => x = 0,
+                    g => {
+                        let w = utils::grapheme_width(g);
+                        if w > 0 {
+                            self.put(x, y, g, w, &style);
+                            x += w;
+                        }
+                    }
+                }
+            }
+            rest = &rest[text_end..];
+            if rest.is_empty() {
+                break;
+            }
+
+            // Only CSI sequences are emitted by termion, anything else is skipped
+            if !rest.starts_with("\x1B[") {
+                rest = &rest[1..];
+                continue;
+            }
+            let end = match rest[2..].find(|c: char| ('@'..='~').contains(&c)) {
+                Some(end) => end + 2,
+                None => break,
+            };
+            let params = &rest[2..end];
+            let n = |default: usize| params.parse::<usize>().unwrap_or(default);
+            match &rest[end..=end] {
+                "H" => {
+                    let mut ps = params.split(';').map(|p| p.parse::<usize>().unwrap_or(1));
+                    y = ps.next().unwrap_or(1).saturating_sub(1);
+                    x = ps.next().unwrap_or(1).saturating_sub(1);
+                }
+                "A" => y = y.saturating_sub(n(1)),
+                "B" => y += n(1),
+                "C" => x += n(1),
+                "D" => x = x.saturating_sub(n(1)),
+                "J" => {
+                    let pos = self.idx(x, y).unwrap_or(self.cells.len());
+                    let range = match n(0) {
+                        0 => pos..self.cells.len(),
+                        1 => 0..cmp::min(pos + 1, self.cells.len()),
+                        _ => 0..self.cells.len(),
+                    };
+                    for c in &mut self.cells[range] {
+                        *c = Cell::default();
+                    }
+                }
+                "K" if y < self.height as usize => {
+                    let start = y * self.width as usize;
+                    let end = start + self.width as usize;
+                    let pos = start + cmp::min(x, self.width as usize);
+                    let range = match n(0) {
+                        0 => pos..end,
+                        1 => start..cmp::min(pos + 1, end),
+                        _ => start..end,
+                    };
+                    for c in &mut self.cells[range] {
+                        *c = Cell::default();
+                    }
+                }
+                "m" if !params.starts_with('?') => style.apply(params),
+                _ => {}
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+
+    fn idx(&self, x: usize, y: usize) -> Option<usize> {
+        if x < self.width as usize && y < self.height as usize {
+            Some(y * self.width as usize + x)
+        } else {
+            None
+        }
+    }
+
+    fn put(&mut self, x: usize, y: usize, g: &str, w: usize, style: &Style) {
+        // Graphemes that do not fit are clipped instead of wrapped
+        let i = match (self.idx(x, y), self.idx(x + w - 1, y)) {
+            (Some(i), Some(_)) => i,
+            _ => return,
+        };
+        // Don't leave halves of overwritten wide graphemes behind
+        if x > 0 && self.cells[i].g.is_empty() {
+            self.cells[i - 1].g = " ".to_owned();
+        }
+        let after = i + w;
+        if after < self.cells.len() && x + w < self.width as usize && self.cells[after].g.is_empty()
+        {
+            self.cells[after].g = " ".to_owned();
+        }
+
+        self.cells[i] = Cell {
+            g: g.to_owned(),
+            style: style.clone(),
+        };
+        for c in &mut self.cells[i + 1..i + w] {
+            *c = Cell {
+                g: String::new(),
+                style: style.clone(),
+            };
+        }
+    }
+
+    fn diff(&self, target: &mut Vec<u8>) {
+        let width = self.width as usize;
+        if width == 0 {
+            return;
+        }
+        let mut style = None;
+
+        for (y, (row, prev)) in self
+            .cells
+            .chunks(width)
+            .zip(self.prev.chunks(width))
+            .enumerate()
+        {
+            let first = match row.iter().zip(prev).position(|(c, p)| c != p) {
+                Some(first) => first,
+                None => continue,
+            };
+            let last = width
+                - 1
+                - row
+                    .iter()
+                    .rev()
+                    .zip(prev.iter().rev())
+                    .position(|(c, p)| c != p)
+                    .unwrap();
+            // Start at the beginning of a wide grapheme, if its right half changed
+            let first = row[..=first]
+                .iter()
+                .rposition(|c| !c.g.is_empty())
+                .unwrap_or(0);
+
+            write!(target, "{}", cursor::Goto(first as u16 + 1, y as u16 + 1)).unwrap();
+            for c in &row[first..=last] {
+                if c.g.is_empty() {
+                    continue;
+                }
+                if style != Some(&c.style) {
+                    c.style.write(target);
+                    style = Some(&c.style);
+                }
+                target.extend_from_slice(c.g.as_bytes());
+            }
+        }
+
+        if style.is_some() {
+            Style::default().write(target);
+        }
+    }
+}
@@ -13,9 +13,10 @@
 // You should have received a copy of the GNU General Public License
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
-use futures::sync::mpsc;
+use futures::sync::{mpsc, oneshot};
 use log::{debug, trace, warn};
 use parking_lot::Mutex;
+use synapse_rpc::message::SMessage;
 use termion::{self, clear, cursor, event::Key, raw::IntoRawMode, screen::AlternateScreen};
 use tokio::{prelude::*, timer};
 use tokio_signal::unix::Signal;
@@ -23,15 +24,22 @@ use tokio_signal::unix::Signal;
 use std::{
     cmp,
     io::{self, Write},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
     time::{Duration, Instant},
 };
 
 use crate::{
-    input,
-    rpc::{Item as RpcItem, WsSink},
-    tui::{panels, widgets, Component, InputResult},
-    utils::{align, color::ColorEscape},
+    config::CONFIG,
+    input::{self, Input},
+    keymap::Action,
+    rpc::{self, Auth, Item as RpcItem, WsSink},
+    state::State,
+    tui::{notify, panels, screen::Screen, widgets, Component, InputResult, Renderable},
+    utils::{self, align, color::ColorEscape},
 };
 
 enum Err {
@@ -47,11 +55,71 @@ where
 {
     Idle,
     Established(E),
-    Pending(P),
+    // The number of the reconnection attempt, or 0 if logging in normally, and a delay to
+    // periodically rerender the connection progress
+    Pending(P, u32, timer::Delay),
+    // Waiting for the delay to pass before retrying the last login
+    Reconnecting(u32, Option<timer::Delay>),
+}
+
+const RECONNECT_ATTEMPTS: u32 = 10;
+
+/// Whether the terminal is in raw mode on the alternate screen
+pub static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// Renders requested by rpc activity are coalesced and performed at most once per frame
+const FRAME_INTERVAL_MS: u64 = 50;
+
+// The frame buffers keep their capacity between frames, unless it exceeds this many times the
+// typical frame size, and is above the minimum
+const BUFFER_SHRINK_FACTOR: usize = 4;
+const MIN_RETAINED_BUFFER: usize = 64 * 1024;
+
+// Clipboard tools can stall, e.g. if the program owning the selection does not respond
+const CLIPBOARD_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Clears the buffer after a frame was written, and frees capacity that only a few unusually large
+// frames needed. typical is an exponential moving average of the frame sizes
+fn recycle_buffer(buf: &mut Vec<u8>, typical: &mut usize) {
+    *typical = (*typical * 7 + buf.len()) / 8;
+    buf.clear();
+    if buf.capacity() > cmp::max(*typical * BUFFER_SHRINK_FACTOR, MIN_RETAINED_BUFFER) {
+        debug!(
+            "Shrinking frame buffer of {} bytes, frames are typically {} bytes",
+            buf.capacity(),
+            typical
+        );
+        buf.shrink_to_fit();
+        buf.reserve(*typical);
+    }
+}
+
+fn progress_tick() -> timer::Delay {
+    timer::Delay::new(Instant::now() + Duration::from_millis(100))
+}
+
+fn reconnect_delay(attempt: u32) -> timer::Delay {
+    let secs = cmp::min(1 << cmp::min(attempt.saturating_sub(1), 5), 30);
+    timer::Delay::new(Instant::now() + Duration::from_secs(secs))
+}
+
+// The panel shown while logged out, the configured servers are picked from if there are several
+fn login_panel() -> Box<dyn Component> {
+    match CONFIG.servers.len() {
+        0 => Box::new(panels::Login::new()),
+        1 => Box::new(panels::Login::with_server(
+            Some(&CONFIG.servers[0].url),
+            Auth::from_config(
+                CONFIG.servers[0].pass.as_ref(),
+                CONFIG.servers[0].token.as_ref(),
+            ),
+        )),
+        _ => Box::new(panels::ServerPicker::new()),
+    }
 }
 
 pub fn run(
-    mut urls: mpsc::Sender<(String, String)>,
+    mut urls: mpsc::Sender<(String, Auth)>,
     mut conns: impl Stream<
         Item = impl Future<
             Item = (
@@ -65,27 +133,66 @@ pub fn run(
 ) -> impl Future<Item = (), Error = ()> {
     let size = termion::terminal_size().unwrap_or((0, 0));
     let mut render_buffer = Vec::with_capacity(size.0 as usize * size.1 as usize + 1);
+    let mut output_buffer = Vec::with_capacity(size.0 as usize * size.1 as usize + 1);
+    // The typical sizes of the render and output of a frame
+    let mut frame_sizes = (render_buffer.capacity(), output_buffer.capacity());
+    // Only the changes between rendered frames are written to the terminal
+    let mut screen = Screen::new();
     // FIXME: Use an unbuffered stdout: `https://github.com/rust-lang/rust/issues/58326`,
     // to avoid the LineWriter
     let mut out = AlternateScreen::from(io::stdout()).into_raw_mode().unwrap();
-    write!(out, "{}", cursor::Hide).unwrap();
+    write!(out, "{}{}", cursor::Hide, input::ENABLE_PASTE).unwrap();
+    ACTIVE.store(true, Ordering::SeqCst);
 
     // Wrap the things that are sent into Futures and shared in Arcs/Mutexes
     let conn1 = Arc::new(Mutex::new(Connection::Idle));
     let conn2 = Arc::clone(&conn1);
     let logged_in1 = Arc::new(Mutex::new(false));
     let logged_in2 = Arc::clone(&logged_in1);
-    let content1 = Arc::new(Mutex::new(Some(
-        Box::new(panels::Login::new()) as Box<Component>
-    )));
+    let content1 = Arc::new(Mutex::new(Some(login_panel())));
     let content2 = Arc::clone(&content1);
     let content3 = Arc::clone(&content1);
     let content4 = Arc::clone(&content1);
+    let content5 = Arc::clone(&content1);
+    let content6 = Arc::clone(&content1);
+    // The last server and password or token a connection was attempted with, used to reconnect
+    let last_login1 = Arc::new(Mutex::new(if CONFIG.autoconnect {
+        CONFIG.server.clone().map(|svr| {
+            let auth = Auth::from_config(CONFIG.pass.as_ref(), CONFIG.token.as_ref())
+                .unwrap_or_else(|| Auth::Password(String::new()));
+            (svr, auth)
+        })
+    } else {
+        None
+    }));
+    let last_login2 = Arc::clone(&last_login1);
+    let reconnect_attempt = Arc::new(AtomicUsize::new(0));
+    let mut urls2 = urls.clone();
+    let dirty1 = Arc::new(AtomicBool::new(false));
+    let dirty2 = Arc::clone(&dirty1);
+    let dirty3 = Arc::clone(&dirty1);
+    // Whether the user is asked to continue despite a version mismatch
+    let mismatch_pending1 = Arc::new(AtomicBool::new(false));
+    let mismatch_pending2 = Arc::clone(&mismatch_pending1);
 
     let interval = timer::Interval::new(Instant::now(), Duration::from_secs(10))
         .map_err(|e| Err::Unrecoverable(("Timer".to_string(), e.to_string())))
         .map(|_| true);
 
+    // Rerender when a notification or highlight expired, so that it disappears without further
+    // input
+    let notifications = timer::Interval::new(Instant::now(), Duration::from_millis(250))
+        .map_err(|e| Err::Unrecoverable(("Timer".to_string(), e.to_string())))
+        .map(move |_| {
+            let content = content5.lock().as_mut().is_some_and(|c| c.expire());
+            notify::expire() || content
+        });
+
+    // Performs the renders that rpc activity requested since the last frame
+    let frames = timer::Interval::new(Instant::now(), Duration::from_millis(FRAME_INTERVAL_MS))
+        .map_err(|e| Err::Unrecoverable(("Timer".to_string(), e.to_string())))
+        .map(move |_| dirty1.swap(false, Ordering::Relaxed));
+
     // SIGWINCH is signalled if the terminal got resized
     // TODO: Do layouting here
     let resize = Signal::new(libc::SIGWINCH)
@@ -95,44 +202,116 @@ pub fn run(
 
     let input = input::stream()
         .map_err(Err::Unrecoverable)
-        .and_then(move |key| match key {
-            Key::Ctrl('q') => {
-                let mut logged_in = logged_in1.lock();
-                if *logged_in {
-                    debug!("Disconnecting");
-
-                    let mut conn = conn1.lock();
-                    let mut content = content1.lock();
-                    *conn = Connection::Idle;
-                    *content = Some(Box::new(panels::Login::new()));
-                    *logged_in = false;
-
-                    Ok(true)
-                } else {
-                    debug!("Quitting");
-                    Err(Err::Shutdown)
-                }
+        // While typing, the clipboard is pasted like text pasted into the terminal, otherwise the
+        // key is passed on. All inputs are single-line, so only its first line is used. The tool is
+        // run on its own thread, so that it can't block the event loop, and the input following it
+        // waits until it is pasted
+        .and_then(move |input| match input {
+            Input::Key(key)
+                if CONFIG.keys.is(Action::Paste, key)
+                    && content6.lock().as_ref().is_some_and(|c| c.typing()) =>
+            {
+                let (s, r) = oneshot::channel();
+                thread::spawn(move || {
+                    // The receiver is gone if the tool timed out
+                    let _ = s.send(utils::paste_from_clipboard());
+                });
+                future::Either::A(timer::Timeout::new(r, CLIPBOARD_TIMEOUT).then(|res| {
+                    let err = |e| Err(Err::Recoverable(("Clipboard".to_owned(), e)));
+                    match res {
+                        Ok(Ok(text)) => {
+                            Ok(Input::Paste(text.lines().next().unwrap_or("").to_owned()))
+                        }
+                        Ok(Err(e)) => err(e),
+                        Err(ref e) if e.is_elapsed() => err(format!(
+                            "The clipboard tool did not respond within {}s",
+                            CLIPBOARD_TIMEOUT.as_secs()
+                        )),
+                        Err(e) => err(format!("Failed to read the clipboard: {}", e)),
+                    }
+                }))
             }
-            key => {
-                let (w, h) = termion::terminal_size().unwrap_or((0, 0));
-                let mut content = content1.lock();
-                match content
-                    .as_mut()
-                    .unwrap_or_else(|| unreachable!())
-                    .input(key, w, h)
+            input => future::Either::B(future::ok(input)),
+        })
+        .and_then(move |input| {
+            // Only quitting falls through, everything else returns whether to render
+            match input {
+                Input::Key(key)
+                    if CONFIG.keys.is_unless_typing(
+                        Action::Quit,
+                        key,
+                        content1.lock().as_ref().is_some_and(|c| c.typing()),
+                    ) =>
                 {
-                    InputResult::ReplaceWith(other) => {
-                        *content = Some(other);
-                        Ok(true)
+                    let mut content = content1.lock();
+                    if CONFIG.confirm.contains(&Action::Quit)
+                        && content.as_ref().is_some_and(|c| c.transferring())
+                    {
+                        *content = Some(Box::new(panels::Confirm::new(
+                            "Torrents are transferring, quit?".to_owned(),
+                            Action::Quit,
+                            content.take().unwrap_or_else(|| unreachable!()),
+                        )));
+                        return Ok(true);
                     }
-                    InputResult::ConnectWith(svr, pass) => {
-                        urls.try_send((svr, pass)).unwrap();
-                        Ok(false)
+                }
+                Input::Key(Key::Char('d')) if mismatch_pending1.load(Ordering::Relaxed) => {}
+                input => {
+                    if let Input::Key(Key::Char('c')) | Input::Key(Key::Esc) = input {
+                        mismatch_pending1.store(false, Ordering::Relaxed);
+                    }
+                    let (w, h) = termion::terminal_size().unwrap_or((0, 0));
+                    let mut content = content1.lock();
+                    let res = {
+                        let content = content.as_mut().unwrap_or_else(|| unreachable!());
+                        match input {
+                            Input::Key(key) => content.input(key, w, h),
+                            Input::Paste(text) => content.paste(&text, w, h),
+                        }
+                    };
+                    match res {
+                        InputResult::ReplaceWith(other) => {
+                            *content = Some(other);
+                            return Ok(true);
+                        }
+                        InputResult::ConnectWith(svr, auth) => {
+                            *last_login1.lock() = Some((svr.clone(), auth.clone()));
+                            // Only fails if a connection is already being made
+                            return match urls.try_send((svr, auth)) {
+                                Ok(()) => Ok(false),
+                                Err(e) => Err(Err::Recoverable((
+                                    "Connection".to_owned(),
+                                    format!("Failed to connect: {}", e),
+                                ))),
+                            };
+                        }
+                        InputResult::Rerender => return Ok(true),
+                        InputResult::Quit => {}
+                        _ => return Ok(false),
                     }
-                    InputResult::Rerender => Ok(true),
-                    _ => Ok(false),
                 }
             }
+
+            mismatch_pending1.store(false, Ordering::Relaxed);
+            let mut logged_in = logged_in1.lock();
+            if *logged_in {
+                debug!("Disconnecting");
+
+                let mut conn = conn1.lock();
+                let mut content = content1.lock();
+                // The connection is still alive, so the server can be told to stop sending
+                if let Some(ref mut c) = *content {
+                    c.teardown();
+                }
+                *conn = Connection::Idle;
+                *content = Some(login_panel());
+                *logged_in = false;
+
+                Ok(true)
+            } else {
+                debug!("Quitting");
+                Err(Err::Shutdown)
+            }
         });
 
     let rpc = stream::poll_fn(move || {
@@ -143,19 +322,93 @@ pub fn run(
             Connection::Idle => match conns.poll() {
                 Err(e) => std::result::Result::Err(Err::Recoverable(e)),
                 Ok(Async::Ready(Some(fut))) => {
-                    *conn = Connection::Pending(fut);
+                    *conn = Connection::Pending(fut, 0, progress_tick());
+                    if let Some(ref mut c) = *content2.lock() {
+                        c.connecting(Some(Instant::now()));
+                    }
+                    // Make sure the connection future gets polled
+                    task::current().notify();
                     Ok(Async::NotReady)
                 }
                 _ => Ok(Async::NotReady),
             },
-            Connection::Pending(ref mut c) => match c.poll() {
+            Connection::Reconnecting(attempt, ref mut delay) => {
+                if let Some(ref mut d) = delay {
+                    match d.poll() {
+                        Err(e) => {
+                            return std::result::Result::Err(Err::Unrecoverable((
+                                "Timer".to_string(),
+                                e.to_string(),
+                            )));
+                        }
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Ok(Async::Ready(())) => {
+                            debug!("Reconnecting, attempt {}", attempt);
+                            let sent = match last_login2.lock().clone() {
+                                Some(login) => urls2.try_send(login).map_err(|e| e.to_string()),
+                                None => Err("there is no server to reconnect to".to_owned()),
+                            };
+                            if let Err(e) = sent {
+                                warn!("Failed to reconnect: {}", e);
+                                let mut content = content2.lock();
+                                let mut logged_in = logged_in2.lock();
+                                *content = Some(login_panel());
+                                *logged_in = false;
+                                *conn = Connection::Idle;
+                                return std::result::Result::Err(Err::Recoverable((
+                                    "Connection".to_owned(),
+                                    format!("Failed to reconnect: {}", e),
+                                )));
+                            }
+                        }
+                    }
+                }
+                *delay = None;
+
+                match conns.poll() {
+                    Err(e) => std::result::Result::Err(Err::Recoverable(e)),
+                    Ok(Async::Ready(Some(fut))) => {
+                        *conn = Connection::Pending(fut, attempt, progress_tick());
+                        task::current().notify();
+                        Ok(Async::NotReady)
+                    }
+                    _ => Ok(Async::NotReady),
+                }
+            }
+            Connection::Pending(ref mut c, attempt, ref mut tick) => match c.poll() {
                 Err(e) => {
-                    *conn = Connection::Idle;
-                    std::result::Result::Err(Err::Recoverable(e))
+                    if attempt > 0 && attempt < RECONNECT_ATTEMPTS && rpc::is_connection_error(&e) {
+                        warn!("Reconnection attempt {} failed: {}", attempt, e.1);
+                        reconnect_attempt.store(attempt as usize + 1, Ordering::Relaxed);
+                        *conn = Connection::Reconnecting(
+                            attempt + 1,
+                            Some(reconnect_delay(attempt + 1)),
+                        );
+                        task::current().notify();
+
+                        Ok(Async::Ready(Some(true)))
+                    } else {
+                        let mut content = content2.lock();
+                        if attempt > 0 {
+                            let mut logged_in = logged_in2.lock();
+                            *content = Some(login_panel());
+                            *logged_in = false;
+                        } else if let Some(ref mut c) = *content {
+                            c.connecting(None);
+                        }
+                        *conn = Connection::Idle;
+                        std::result::Result::Err(Err::Recoverable(e))
+                    }
                 }
                 Ok(Async::Ready((sink, stream))) => {
                     *conn = Connection::Established(stream);
 
+                    if let Some((ref svr, _)) = *last_login2.lock() {
+                        let mut state = State::load();
+                        state.push_server(svr.clone());
+                        state.save();
+                    }
+
                     let mut content = content2.lock();
                     let height = termion::terminal_size().unwrap_or((0, 0)).1;
                     *content = Some(Box::new(panels::Main::new(&sink, height)));
@@ -165,18 +418,128 @@ pub fn run(
 
                     Ok(Async::Ready(Some(true)))
                 }
-                _ => Ok(Async::NotReady),
+                // Rerender regularly to animate the connection progress
+                _ => match tick.poll() {
+                    Ok(Async::Ready(())) => {
+                        tick.reset(Instant::now() + Duration::from_millis(100));
+                        Ok(Async::Ready(Some(true)))
+                    }
+                    Ok(Async::NotReady) => Ok(Async::NotReady),
+                    Err(e) => std::result::Result::Err(Err::Unrecoverable((
+                        "Timer".to_string(),
+                        e.to_string(),
+                    ))),
+                },
             },
             Connection::Established(ref mut c) => match c.poll() {
+                Err(e)
+                    if CONFIG.reconnect
+                        && rpc::is_connection_error(&e)
+                        && last_login2.lock().is_some() =>
+                {
+                    warn!("Connection lost, reconnecting: {}: {}", e.0, e.1);
+                    mismatch_pending2.store(false, Ordering::Relaxed);
+                    reconnect_attempt.store(1, Ordering::Relaxed);
+                    *conn = Connection::Reconnecting(1, Some(reconnect_delay(1)));
+                    task::current().notify();
+
+                    let mut content = content2.lock();
+                    let draw = |target: &mut _, width, _, x, y, state: &mut Arc<AtomicUsize>| {
+                        widgets::Text::<_, align::x::Center, align::y::Top>::new(
+                            true,
+                            format!(
+                                "Reconnecting, attempt {} of {}…",
+                                state.load(Ordering::Relaxed),
+                                RECONNECT_ATTEMPTS
+                            ),
+                        )
+                        .render(target, width, 1, x, y);
+                    };
+                    *content = Some(Box::new(
+                        widgets::OwnedOverlay::new(
+                            widgets::CloseOnInput::new(
+                                widgets::IgnoreRpc::new(widgets::RenderStateFn::new(
+                                    draw,
+                                    Arc::clone(&reconnect_attempt),
+                                )),
+                                &[Key::Esc],
+                            ),
+                            content.take().unwrap_or_else(|| unreachable!()),
+                            (32, 1),
+                            Some(ColorEscape::error()),
+                            "Connection lost".to_owned(),
+                        )
+                        // The panel below no longer receives updates
+                        .dim_below(),
+                    ));
+
+                    Ok(Async::Ready(Some(true)))
+                }
                 Err(e) => {
+                    mismatch_pending2.store(false, Ordering::Relaxed);
                     let mut content = content2.lock();
                     let mut logged_in = logged_in2.lock();
-                    *content = Some(Box::new(panels::Login::new()));
+                    *content = Some(login_panel());
                     *logged_in = false;
                     *conn = Connection::Idle;
 
                     std::result::Result::Err(Err::Recoverable(e))
                 }
+                // Requests the server rejected, e.g. moving a torrent to an invalid path
+                Ok(Async::Ready(Some(RpcItem::Msg(msg @ SMessage::InvalidRequest(_)))))
+                | Ok(Async::Ready(Some(RpcItem::Msg(msg @ SMessage::InvalidResource(_)))))
+                | Ok(Async::Ready(Some(RpcItem::Msg(msg @ SMessage::PermissionDenied(_)))))
+                | Ok(Async::Ready(Some(RpcItem::Msg(msg @ SMessage::TransferFailed(_))))) => {
+                    let reason = match msg {
+                        SMessage::InvalidRequest(ref e)
+                        | SMessage::InvalidResource(ref e)
+                        | SMessage::PermissionDenied(ref e)
+                        | SMessage::TransferFailed(ref e) => e.reason.clone(),
+                        _ => unreachable!(),
+                    };
+                    // The panel may still be waiting on the rejected request
+                    content2
+                        .lock()
+                        .as_mut()
+                        .unwrap_or_else(|| unreachable!())
+                        .rpc(msg);
+                    std::result::Result::Err(Err::Recoverable(("Server".to_string(), reason)))
+                }
+                Ok(Async::Ready(Some(RpcItem::VersionMismatch(ver)))) => {
+                    mismatch_pending2.store(true, Ordering::Relaxed);
+
+                    let text = format!(
+                        "Server rpc version {}.{} differs from {}.{}, [c]ontinue or [d]isconnect?",
+                        ver.major,
+                        ver.minor,
+                        synapse_rpc::MAJOR_VERSION,
+                        synapse_rpc::MINOR_VERSION
+                    );
+                    let mut content = content2.lock();
+                    // The protocol is usually still compatible, so the server version is shown
+                    // like for matching versions
+                    content
+                        .as_mut()
+                        .unwrap_or_else(|| unreachable!())
+                        .rpc(SMessage::RpcVersion(ver));
+                    let len = text.len();
+                    *content = Some(Box::new(widgets::OwnedOverlay::new(
+                        widgets::CloseOnInput::new(
+                            widgets::IgnoreRpc::new(widgets::Text::<
+                                _,
+                                align::x::Center,
+                                align::y::Top,
+                            >::new(true, text)),
+                            &[Key::Char('c'), Key::Char('d'), Key::Esc],
+                        ),
+                        content.take().unwrap_or_else(|| unreachable!()),
+                        (len as _, 1),
+                        Some(ColorEscape::error()),
+                        "Version mismatch".to_owned(),
+                    )));
+
+                    Ok(Async::Ready(Some(true)))
+                }
                 Ok(Async::Ready(Some(RpcItem::Msg(msg)))) => {
                     let mut content = content2.lock();
                     let content = content.as_mut().unwrap_or_else(|| unreachable!());
@@ -185,6 +548,13 @@ pub fn run(
                 _ => Ok(Async::NotReady),
             },
         }
+    })
+    .map(move |render| {
+        // On busy servers nearly every message causes a rerender, so only mark the frame as dirty
+        if render {
+            dirty2.store(true, Ordering::Relaxed);
+        }
+        false
     });
 
     // This futurefied stream first selects on:
@@ -192,6 +562,8 @@ pub fn run(
     // 2) rpc activity
     // 3) SIGWINCH, to handle resizing
     // 4) a 10s interval, to regularly update the server uptime
+    // 5) a short interval, to remove expired notifications and highlights
+    // 6) the frame interval, to render what rpc activity changed
     // If no error occured, the selected value is a bool that if true causes a rendering pass
     // handled via a for_each.
     // In case of an error it is checked what kind of error: Shutdown. Recoverable, or Unrecoverable.
@@ -201,30 +573,18 @@ pub fn run(
     // Before the application stops, all internally spawned tasks are waited upon, so any remaining
     // rpc msg send operations are completed.
     input
-        .select(rpc.select(resize.select(interval)))
+        .select(rpc.select(resize.select(interval.select(notifications.select(frames)))))
         .or_else(move |e| match e {
             Err::Recoverable((name, text)) => {
                 warn!("Recoverable err in {}: {}", name, text);
 
                 let mut content = content3.lock();
-                let len = cmp::max(text.len(), name.len()) + 2;
-                let overlay = Box::new(widgets::OwnedOverlay::new(
-                    widgets::CloseOnInput::new(
-                        widgets::IgnoreRpc::new(
-                            widgets::Text::<_, align::x::Center, align::y::Top>::new(true, text),
-                        ),
-                        &[
-                            Key::Esc,
-                            Key::Backspace,
-                            Key::Delete,
-                            Key::Char('q'),
-                            Key::Char('\n'),
-                        ],
-                    ),
+                let width = termion::terminal_size().map_or(u16::MAX, |(w, _)| w.saturating_sub(4));
+                let overlay = Box::new(widgets::error_overlay(
+                    name,
+                    text,
                     content.take().unwrap_or_else(|| unreachable!()),
-                    (len as _, 1),
-                    Some(ColorEscape::red()),
-                    Some(name),
+                    width,
                 ));
                 *content = Some(overlay);
 
@@ -236,33 +596,94 @@ pub fn run(
             let err = |t: io::Error| Err::Unrecoverable(("Render".to_string(), t.to_string()));
             if render {
                 trace!("Rendering");
+                // Any pending rpc changes are included in this pass
+                dirty3.store(false, Ordering::Relaxed);
                 if let Ok((width, height)) = termion::terminal_size() {
+                    if width < CONFIG.min_width || height < CONFIG.min_height {
+                        write!(render_buffer, "{}", clear::All).map_err(err)?;
+                        widgets::Text::<_, align::x::Center, align::y::Center>::new(
+                            true,
+                            format!(
+                                "Terminal too small (need {}x{})",
+                                CONFIG.min_width, CONFIG.min_height
+                            ),
+                        )
+                        .render(&mut render_buffer, width, height, 1, 1);
+                        screen.update(&render_buffer, width, height, &mut output_buffer);
+                        out.write_all(&output_buffer).map_err(err)?;
+                        out.flush().map_err(err)?;
+                        recycle_buffer(&mut render_buffer, &mut frame_sizes.0);
+                        recycle_buffer(&mut output_buffer, &mut frame_sizes.1);
+                        return Ok(());
+                    }
+
                     let mut content = content4.lock();
                     let content = content.as_mut().unwrap_or_else(|| unreachable!());
                     write!(render_buffer, "{}", clear::All).map_err(err)?;
                     content.render(&mut render_buffer, width, height, 1, 1);
+                    // Notifications replace the footer line while they are shown
+                    if let Some(msg) = notify::current() {
+                        write!(
+                            render_buffer,
+                            "{}{}",
+                            cursor::Goto(1, height),
+                            clear::CurrentLine
+                        )
+                        .map_err(err)?;
+                        widgets::Text::<_, align::x::Left, align::y::Top>::new(true, msg).render(
+                            &mut render_buffer,
+                            width,
+                            1,
+                            1,
+                            height,
+                        );
+                    }
 
-                    out.write_all(&*render_buffer).map_err(err)?;
+                    screen.update(&render_buffer, width, height, &mut output_buffer);
+                    out.write_all(&output_buffer).map_err(err)?;
                     out.flush().map_err(err)?;
-                    render_buffer.clear();
+                    recycle_buffer(&mut render_buffer, &mut frame_sizes.0);
+                    recycle_buffer(&mut output_buffer, &mut frame_sizes.1);
                 } else {
+                    screen.invalidate();
                     write!(out, "smol").map_err(err)?;
                     out.flush().map_err(err)?;
                 }
             }
             Ok(())
         })
-        .map_err(|e| match e {
-            Err::Shutdown => (),
-            Err::Recoverable(_) => unreachable!(),
-            Err::Unrecoverable((name, text)) => {
-                warn!("Unrecoverable error in {}: {}", name, text);
-                println!("Unrecoverable error in {}: {}", name, text);
-            }
-        })
-        .then(|_| {
+        .then(|res| {
             debug!("View finishing");
-            print!("{}", cursor::Show);
+            // The terminal is restored at this point, so the error doesn't draw over the TUI
+            print!("{}{}", input::DISABLE_PASTE, cursor::Show);
+            ACTIVE.store(false, Ordering::SeqCst);
+            match res {
+                Ok(()) | Err(Err::Shutdown) => {}
+                Err(Err::Recoverable(_)) => unreachable!(),
+                Err(Err::Unrecoverable((name, text))) => {
+                    warn!("Unrecoverable error in {}: {}", name, text);
+                    println!("Unrecoverable error in {}: {}", name, text);
+                }
+            }
             Ok(())
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recycles_buffers() {
+        let mut typical = 1000;
+        let mut buf = vec![0; 1000];
+        recycle_buffer(&mut buf, &mut typical);
+        assert!(buf.is_empty() && buf.capacity() >= 1000);
+
+        // A single huge frame doesn't keep its memory around
+        let mut buf = vec![0; 10 * MIN_RETAINED_BUFFER];
+        recycle_buffer(&mut buf, &mut typical);
+        assert!(buf.is_empty());
+        assert_eq!(buf.capacity(), typical);
+    }
+}
@@ -19,11 +19,14 @@ use unicode_segmentation::UnicodeSegmentation;
 
 use std::{
     borrow::{Borrow, BorrowMut},
+    cmp,
     io::Write,
+    iter,
     marker::PhantomData,
     mem::ManuallyDrop,
     ops::Range,
     str,
+    time::Instant,
 };
 
 use crate::{
@@ -42,6 +45,23 @@ pub enum Unit {
     Percent(f32),
 }
 
+// The sizes of the first child, the divider, and the second child of a split. Both children get at
+// least min if there is enough space, and the divider is omitted if there is no room for it
+fn split_sizes(space: u16, first: &Unit, min: u16, draw_div: bool) -> (u16, u16, u16) {
+    let div = if draw_div && space > min.saturating_mul(2) {
+        1
+    } else {
+        0
+    };
+    let avail = space - div;
+    let first = match *first {
+        Unit::Lines(l) => l,
+        Unit::Percent(p) => (f32::from(space) * p).floor() as u16,
+    };
+    let first = cmp::min(cmp::max(first, min), avail.saturating_sub(min));
+    (first, div, avail - first)
+}
+
 pub struct VSplit<'a, L: 'a, R: 'a>
 where
     L: BorrowMut<Renderable + 'a> + Send,
@@ -52,6 +72,8 @@ where
     left_active: Option<bool>,
     left_size: Unit,
     draw_div: bool,
+    // The minimum width of either side
+    min: u16,
     _marker: PhantomData<&'a ()>,
 }
 
@@ -73,9 +95,15 @@ where
             left_active,
             left_size,
             draw_div,
+            min: 1,
             _marker: PhantomData,
         }
     }
+
+    pub fn min_size(mut self, min: u16) -> VSplit<'a, L, R> {
+        self.min = min;
+        self
+    }
 }
 
 impl<'a, L, R> Renderable for VSplit<'a, L, R>
@@ -84,42 +112,33 @@ where
     R: BorrowMut<Renderable + 'a> + Send,
 {
     fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
+        let (left_w, comp, right_w) = split_sizes(width, &self.left_size, self.min, self.draw_div);
+
         // Draw left
-        let left_w = match self.left_size {
-            Unit::Lines(w) => w,
-            Unit::Percent(p) => (f32::from(width) * p).floor() as u16,
-        };
         self.left
             .borrow_mut()
             .render(target, left_w, height, x_off, y_off);
 
-        let comp = if self.draw_div {
+        if comp == 1 {
             // Draw divider
             for i in 0..height {
                 write!(target, "{}{}", cursor::Goto(x_off + left_w, y_off + i), {
                     if self.left_active.unwrap_or(false) && i < height / 2
                         || !self.left_active.unwrap_or(true) && i > height / 2
                     {
-                        format!("{}│{}", ColorEscape::cyan(), ColorEscape::reset())
+                        format!("{}│{}", ColorEscape::divider(), ColorEscape::reset())
                     } else {
                         "│".into()
                     }
                 })
                 .unwrap();
             }
-            1
-        } else {
-            0
-        };
+        }
 
         // Draw right
-        self.right.borrow_mut().render(
-            target,
-            width - left_w - comp,
-            height,
-            x_off + left_w + comp,
-            y_off,
-        );
+        self.right
+            .borrow_mut()
+            .render(target, right_w, height, x_off + left_w + comp, y_off);
     }
 }
 
@@ -133,6 +152,8 @@ where
     top_active: Option<bool>,
     top_size: Unit,
     draw_div: bool,
+    // The minimum height of either side
+    min: u16,
     _marker: PhantomData<&'a ()>,
 }
 
@@ -154,9 +175,15 @@ where
             top_active,
             top_size,
             draw_div,
+            min: 1,
             _marker: PhantomData,
         }
     }
+
+    pub fn min_size(mut self, min: u16) -> HSplit<'a, T, B> {
+        self.min = min;
+        self
+    }
 }
 
 impl<'a, T: 'a, B: 'a> Renderable for HSplit<'a, T, B>
@@ -172,22 +199,20 @@ where
         )
     }
     fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
+        let (top_h, comp, bot_h) = split_sizes(height, &self.top_size, self.min, self.draw_div);
+
         // Draw top
-        let top_h = match self.top_size {
-            Unit::Lines(h) => h,
-            Unit::Percent(p) => (f32::from(height) * p).floor() as u16,
-        };
         self.top
             .borrow_mut()
             .render(target, width, top_h, x_off, y_off);
 
-        let comp = if self.draw_div {
+        if comp == 1 {
             // Draw divider
             let div = (0..width).fold("".to_owned(), |acc, i| {
                 if self.top_active.unwrap_or(false) && i == 0
                     || !self.top_active.unwrap_or(true) && i == width / 2
                 {
-                    acc + &*format!("{}─", ColorEscape::cyan())
+                    acc + &*format!("{}─", ColorEscape::divider())
                 } else if self.top_active.unwrap_or(false) && i == width / 2
                     || !self.top_active.unwrap_or(true) && i == width
                 {
@@ -197,19 +222,12 @@ where
                 }
             });
             write!(target, "{}{}", cursor::Goto(x_off, y_off + top_h), div).unwrap();
-            1
-        } else {
-            0
-        };
+        }
 
         // Draw bot
-        self.bot.borrow_mut().render(
-            target,
-            width,
-            height - top_h - comp,
-            x_off,
-            y_off + top_h + comp,
-        );
+        self.bot
+            .borrow_mut()
+            .render(target, width, bot_h, x_off, y_off + top_h + comp);
     }
 }
 
@@ -232,6 +250,30 @@ where
     }
 }
 
+// The tabs around the active one whose names fit into width, given the name lengths
+fn visible_tabs(lens: &[u16], active: usize, width: u16) -> Range<usize> {
+    // Every name is padded by at least one divider on each side
+    let needed = |len: u16| len + 2;
+    let mut window = active..active + 1;
+    let mut used = needed(lens[active]);
+    loop {
+        let mut grown = false;
+        if window.end < lens.len() && used + needed(lens[window.end]) <= width {
+            used += needed(lens[window.end]);
+            window.end += 1;
+            grown = true;
+        }
+        if window.start > 0 && used + needed(lens[window.start - 1]) <= width {
+            used += needed(lens[window.start - 1]);
+            window.start -= 1;
+            grown = true;
+        }
+        if !grown {
+            return window;
+        }
+    }
+}
+
 impl<'a, T> Renderable for BorrowedSameTabs<'a, T>
 where
     T: Renderable + 'a + Send,
@@ -240,28 +282,55 @@ where
         write!(target, "{}", cursor::Goto(x_off, y_off)).unwrap();
 
         // Draw header
-        let n_tabs = self.tabs.len();
+        let mut names: Vec<_> = self
+            .tabs
+            .iter()
+            .map(|t| {
+                let name = t.borrow().name();
+                let len = utils::count_without_styling(&name);
+                (name, len)
+            })
+            .collect();
+        let lens: Vec<_> = names.iter().map(|&(_, len)| len).collect();
+
+        // If not all names fit, only show those around the active tab and how many are hidden
+        let mut window = visible_tabs(&lens, self.active_idx, width);
+        let more = if window.len() == names.len() {
+            String::new()
+        } else {
+            // Making room for the indicator may hide even more tabs
+            let reserve = utils::count(&format!(" ◂ {} more ▸", names.len())) as u16;
+            window = visible_tabs(&lens, self.active_idx, width.saturating_sub(reserve));
+            format!(" ◂ {} more ▸", names.len() - window.len())
+        };
+        let more_len = utils::count(&more) as u16;
+        let width = width.saturating_sub(more_len);
+        let first = window.start;
+        names.truncate(window.end);
+        names.drain(..window.start);
+
+        let n_tabs = names.len();
         let sec_len = width / n_tabs as u16;
-        let div_budget = "─".repeat(width.saturating_sub(self.tabs.iter().fold(0, |acc, t| {
-            acc + utils::count_without_styling(&t.borrow().name())
-        })) as usize);
+        let div_budget = "─".repeat(
+            width.saturating_sub(names.iter().fold(0, |acc, &(_, len)| acc + len)) as usize,
+        );
         let mut div_budget = div_budget.chars();
         let div_budget = div_budget.by_ref();
-        for (i, t) in self.tabs.iter_mut().enumerate() {
-            let t = (*t).borrow_mut();
-            let div_len = sec_len.saturating_sub(utils::count_without_styling(&t.name())) / 2;
+        for (i, (name, len)) in names.into_iter().enumerate() {
+            let div_len = sec_len.saturating_sub(len) / 2;
+            let i_tab = first + i;
             write!(
                 target,
                 "{}{}",
-                if self.active_idx == i {
-                    format!("{}", ColorEscape::cyan())
+                if self.active_idx == i_tab {
+                    format!("{}", ColorEscape::selection())
                 } else {
                     "".to_owned()
                 },
                 div_budget.take(div_len as usize + 1).collect::<String>(),
             )
             .unwrap();
-            Text::<_, align::x::Left, align::y::Top>::new(false, t.name()).render(
+            Text::<_, align::x::Left, align::y::Top>::new(false, name).render(
                 target,
                 // FIXME: Width too small if content truncated
                 sec_len,
@@ -277,7 +346,7 @@ where
                 } else {
                     div_budget.take(div_len as usize + 1).collect()
                 },
-                if self.active_idx == i {
+                if self.active_idx == i_tab {
                     ColorEscape::reset()
                 } else {
                     ColorEscape::empty()
@@ -286,6 +355,16 @@ where
             .unwrap();
         }
 
+        if more_len > 0 {
+            Text::<_, align::x::Left, align::y::Top>::new(false, more).render(
+                target,
+                more_len,
+                1,
+                x_off + width,
+                y_off,
+            );
+        }
+
         // Draw active component
         self.tabs[self.active_idx]
             .borrow_mut()
@@ -303,6 +382,7 @@ where
     top_dimensions: (u16, u16),
     box_color: Option<&'a ColorEscape>,
     name: Option<&'a str>,
+    dim_below: bool,
 }
 
 impl<'a, T, B> BorrowedOverlay<'a, T, B>
@@ -323,8 +403,35 @@ where
             top_dimensions,
             box_color,
             name: name.into(),
+            dim_below: false,
+        }
+    }
+
+    /// Renders the lower layer faint, e.g. if its content is stale
+    pub fn dim_below(mut self, dim: bool) -> BorrowedOverlay<'a, T, B> {
+        self.dim_below = dim;
+        self
+    }
+}
+
+// Copies the render to target, and reapplies the faint style after every other style change so
+// that all of it stays dimmed
+fn dim(render: &[u8], target: &mut Vec<u8>) {
+    write!(target, "{}", style::Faint).unwrap();
+    let (mut in_esc, mut prev) = (false, 0);
+    for &b in render {
+        target.push(b);
+        if b == b'\x1B' {
+            in_esc = true;
+        } else if in_esc && prev != b'\x1B' && (b'@'..=b'~').contains(&b) {
+            in_esc = false;
+            if b == b'm' {
+                write!(target, "{}", style::Faint).unwrap();
+            }
         }
+        prev = b;
     }
+    write!(target, "{}", style::NoFaint).unwrap();
 }
 
 impl<'a, T, B> Renderable for BorrowedOverlay<'a, T, B>
@@ -334,7 +441,13 @@ where
 {
     fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
         // Render lower layer
-        self.below.render(target, width, height, x_off, y_off);
+        if self.dim_below {
+            let mut below = Vec::new();
+            self.below.render(&mut below, width, height, x_off, y_off);
+            dim(&below, target);
+        } else {
+            self.below.render(target, width, height, x_off, y_off);
+        }
 
         let x_off = x_off + (width / 2).saturating_sub(self.top_dimensions.0 / 2 + 1);
         let y_off = y_off + (height / 2).saturating_sub(self.top_dimensions.1 / 2 + 1);
@@ -424,6 +537,7 @@ where
     top_dimensions: (u16, u16),
     box_color: Option<ColorEscape>,
     name: Option<String>,
+    dim_below: bool,
 }
 impl<T> OwnedOverlay<T>
 where
@@ -443,11 +557,69 @@ where
             top_dimensions,
             box_color,
             name: name.into(),
+            dim_below: false,
         }
     }
+
+    /// Renders the lower layer faint, e.g. if its content is stale
+    pub fn dim_below(mut self) -> OwnedOverlay<T> {
+        self.dim_below = true;
+        self
+    }
+}
+
+static ERROR_CLOSE: [Key; 5] = [
+    Key::Esc,
+    Key::Backspace,
+    Key::Delete,
+    Key::Char('q'),
+    Key::Char('\n'),
+];
+
+/// An error box titled with where the error occured, wrapping the text to at most max_width
+/// columns. Closing it shows below again
+pub fn error_overlay(
+    name: String,
+    text: String,
+    below: Box<dyn Component>,
+    max_width: u16,
+) -> OwnedOverlay<CloseOnInput<'static, IgnoreRpc<Text<String, x::Center, y::Top>>>> {
+    let len = utils::count(&text);
+    let width = cmp::max(
+        cmp::min(cmp::max(len, utils::count(&name)) + 2, max_width as usize),
+        1,
+    );
+    let height = cmp::max(len.div_ceil(width), 1);
+    OwnedOverlay::new(
+        CloseOnInput::new(IgnoreRpc::new(Text::new(true, text)), &ERROR_CLOSE),
+        below,
+        (width as u16, height as u16),
+        Some(ColorEscape::error()),
+        name,
+    )
 }
 
-impl<T> Component for OwnedOverlay<T> where T: Component + Send {}
+impl<T> Component for OwnedOverlay<T>
+where
+    T: Component + Send,
+{
+    fn transferring(&self) -> bool {
+        self.below.transferring()
+    }
+    fn typing(&self) -> bool {
+        self.top.typing()
+    }
+    fn connecting(&mut self, since: Option<Instant>) {
+        self.below.connecting(since);
+    }
+    fn expire(&mut self) -> bool {
+        self.top.expire() | self.below.expire()
+    }
+    fn teardown(&mut self) {
+        self.top.teardown();
+        self.below.teardown();
+    }
+}
 
 impl<T> Renderable for OwnedOverlay<T>
 where
@@ -461,6 +633,7 @@ where
             self.box_color.as_ref(),
             self.name.as_ref().map(|s| &s[..]),
         )
+        .dim_below(self.dim_below)
         .render(target, width, height, x_off, y_off)
     }
 }
@@ -470,8 +643,8 @@ where
     T: Component + Send,
 {
     fn rpc(&mut self, msg: SMessage) -> bool {
-        self.top.rpc(msg.clone());
-        self.below.rpc(msg)
+        let top = self.top.rpc(msg.clone());
+        self.below.rpc(msg) || top
     }
 }
 
@@ -487,6 +660,114 @@ where
             ret => ret,
         }
     }
+    fn paste(&mut self, text: &str, w: u16, h: u16) -> InputResult {
+        match self.top.paste(text, w, h) {
+            InputResult::Close => InputResult::ReplaceWith(unsafe {
+                Box::from_raw((&mut **self.below) as *mut Component)
+            }),
+            ret => ret,
+        }
+    }
+}
+
+static BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The most recent values as block characters scaled to the maximum, newest at the right
+pub struct Sparkline(Vec<u64>);
+
+impl Sparkline {
+    pub fn new<I: IntoIterator<Item = u64>>(values: I) -> Sparkline {
+        Sparkline(values.into_iter().collect())
+    }
+}
+
+impl Renderable for Sparkline {
+    fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
+        if width < 2 || height == 0 {
+            return;
+        }
+        let vals = &self.0[self.0.len().saturating_sub(width as usize)..];
+        let max = cmp::max(vals.iter().cloned().max().unwrap_or(0), 1);
+        let line = vals
+            .iter()
+            .map(|&v| BLOCKS[(v * (BLOCKS.len() as u64 - 1) / max) as usize])
+            .collect::<String>();
+        write!(
+            target,
+            "{}{}",
+            cursor::Goto(x_off + width - vals.len() as u16, y_off),
+            line
+        )
+        .unwrap();
+    }
+}
+
+static PARTIAL_BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+
+// The eighths of width that val fills, NaN (e.g. from a ratio with a zero denominator) fills none
+fn gauge_eighths(val: f32, width: u16) -> usize {
+    let val = if val.is_nan() { 0. } else { val.clamp(0., 1.) };
+    (val * f32::from(width) * 8.).round() as usize
+}
+
+// Appends eighths as full blocks, followed by a partial block for the remainder
+fn push_blocks(bar: &mut String, eighths: usize) {
+    bar.extend(iter::repeat_n('█', eighths / 8));
+    if !eighths.is_multiple_of(8) {
+        bar.push(PARTIAL_BLOCKS[eighths % 8 - 1]);
+    }
+}
+
+/// A horizontal bar filled proportionally to a value between 0 and 1. An optional secondary value
+/// is drawn dimmed between the end of the primary fill and its own end
+pub struct Gauge {
+    val: f32,
+    secondary: Option<f32>,
+}
+
+impl Gauge {
+    pub fn new(val: f32) -> Gauge {
+        Gauge {
+            val,
+            secondary: None,
+        }
+    }
+
+    pub fn secondary(mut self, val: f32) -> Gauge {
+        self.secondary = Some(val);
+        self
+    }
+
+    /// The bar as a string that is width columns wide
+    pub fn bar(&self, width: u16) -> String {
+        let prim = gauge_eighths(self.val, width);
+        // The secondary fill starts at the cell after the primary one
+        let prim_cells = prim.div_ceil(8);
+        let sec = self
+            .secondary
+            .map(|s| gauge_eighths(s, width).saturating_sub(prim_cells * 8))
+            .unwrap_or(0);
+        let sec_cells = sec.div_ceil(8);
+
+        let mut bar = String::new();
+        push_blocks(&mut bar, prim);
+        if sec > 0 {
+            bar.push_str(ColorEscape::gauge_secondary().inner());
+            push_blocks(&mut bar, sec);
+            bar.push_str(ColorEscape::reset().inner());
+        }
+        bar.extend(iter::repeat_n(' ', width as usize - prim_cells - sec_cells));
+        bar
+    }
+}
+
+impl Renderable for Gauge {
+    fn render(&mut self, target: &mut Vec<u8>, width: u16, height: u16, x_off: u16, y_off: u16) {
+        if height == 0 {
+            return;
+        }
+        write!(target, "{}{}", cursor::Goto(x_off, y_off), self.bar(width)).unwrap();
+    }
 }
 
 pub struct Text<T, AX, AY>
@@ -541,17 +822,10 @@ where
         }
 
         let content = self.content.borrow();
-        let x_off = x_off
-            + match AX::align_offset(&[content], width) {
-                x::Alignment::Single(x) => x,
-                x::Alignment::Each(v) => {
-                    // TODO: unimpl for n > 1
-                    assert_eq!(1, v.len());
-                    *v.first().unwrap()
-                }
-            };
-        let y_off = y_off + AY::align_offset(&[content], height);
+        // Scanning the escape codes is costly for long lines, so only do it once
         let len = utils::count_without_styling(content);
+        let x_off = x_off + AX::align_offset_len(len, width);
+        let y_off = y_off + AY::align_offset(&[content], height);
 
         if width >= len as u16 {
             do_write!(target, x_off, y_off, "{}{}", "{}", content, self.do_goto);
@@ -669,7 +943,7 @@ where
                         do_write!(
                             target,
                             x_off,
-                            y_off + 1,
+                            y_off + i,
                             "{}{}",
                             "{}",
                             format!("{}{}", chunk, n_chunk),
@@ -679,7 +953,7 @@ where
                     }
                 } else {
                     // Last chunk
-                    do_write!(target, x_off, y_off + 1, "{}{}", "{}", chunk, self.do_goto);
+                    do_write!(target, x_off, y_off + i, "{}{}", "{}", chunk, self.do_goto);
                     break;
                 }
             }
@@ -726,7 +1000,58 @@ impl Input {
     }
 
     pub fn end(&mut self) {
-        self.pos = self.content.len() + 1
+        self.pos = self.content.graphemes(true).count() + 1
+    }
+
+    // The grapheme index of the start of the word before the cursor
+    fn word_start(&self) -> usize {
+        let gs = self.content.graphemes(true).collect::<Vec<_>>();
+        let mut p = self.pos - 1;
+        while p > 0 && gs[p - 1].trim().is_empty() {
+            p -= 1;
+        }
+        while p > 0 && !gs[p - 1].trim().is_empty() {
+            p -= 1;
+        }
+        p
+    }
+    pub fn word_before_cursor(&self) -> String {
+        self.content
+            .graphemes(true)
+            .take(self.pos - 1)
+            .skip(self.word_start())
+            .collect()
+    }
+    pub fn word_left(&mut self) {
+        self.pos = self.word_start() + 1;
+    }
+    pub fn word_right(&mut self) {
+        let gs = self.content.graphemes(true).collect::<Vec<_>>();
+        let mut p = self.pos - 1;
+        while p < gs.len() && gs[p].trim().is_empty() {
+            p += 1;
+        }
+        while p < gs.len() && !gs[p].trim().is_empty() {
+            p += 1;
+        }
+        self.pos = p + 1;
+    }
+    pub fn kill_to_end(&mut self) {
+        self.content = self.content.graphemes(true).take(self.pos - 1).collect();
+    }
+    pub fn kill_to_start(&mut self) {
+        self.content = self.content.graphemes(true).skip(self.pos - 1).collect();
+        self.pos = 1;
+    }
+    pub fn delete_word(&mut self) {
+        let start = self.word_start();
+        self.content = self
+            .content
+            .graphemes(true)
+            .take(start)
+            .chain(self.content.graphemes(true).skip(self.pos - 1))
+            .collect();
+        self.pos = start + 1;
     }
 
     pub fn cursor_left(&mut self) {
@@ -750,6 +1075,18 @@ impl Input {
         self.content.insert(offset, c);
         self.pos += 1;
     }
+    /// Inserts pasted text at the cursor, without any line breaks or other control characters
+    pub fn push_str(&mut self, s: &str) {
+        let offset = self
+            .content
+            .graphemes(true)
+            .take(self.pos - 1)
+            .map(str::len)
+            .sum();
+        let s = s.chars().filter(|c| !c.is_control()).collect::<String>();
+        self.content.insert_str(offset, &s);
+        self.pos = self.content[..offset + s.len()].graphemes(true).count() + 1;
+    }
     pub fn backspace(&mut self) {
         if self.pos > 1 {
             self.content = self
@@ -814,31 +1151,78 @@ impl Input {
     pub fn format_inactive(&self) -> &str {
         &self.content
     }
+    /// Formats the content with the byte ranges in `spans` wrapped in `start` and `end`, and the
+    /// cursor underlined if active
+    pub fn format_highlighted(
+        &self,
+        active: bool,
+        spans: &[(usize, usize)],
+        start: &str,
+        end: &str,
+    ) -> String {
+        let mut res = String::with_capacity(self.content.len());
+        let mut in_span = false;
+        for (i, (b, g)) in self.content.grapheme_indices(true).enumerate() {
+            let hl = spans.iter().any(|&(s, e)| b >= s && b < e);
+            if hl != in_span {
+                res.push_str(if hl { start } else { end });
+                in_span = hl;
+            }
+            if active && i + 1 == self.pos {
+                res.push_str(&format!("{}{}{}", style::Underline, g, style::NoUnderline));
+            } else {
+                res.push_str(g);
+            }
+        }
+        if in_span {
+            res.push_str(end);
+        }
+        if active && self.pos > self.content.graphemes(true).count() {
+            res.push_str(&format!("{} {}", style::Underline, style::NoUnderline));
+        }
+        res
+    }
 }
 
 #[derive(Clone)]
-pub struct PasswordInput(Input);
+pub struct PasswordInput {
+    input: Input,
+    // Whether the content is shown instead of stars
+    revealed: bool,
+}
 impl PasswordInput {
     pub fn from<T: Into<Option<usize>>>(content: String, pos: T) -> PasswordInput {
-        PasswordInput(Input::from(content, pos))
+        PasswordInput {
+            input: Input::from(content, pos),
+            revealed: false,
+        }
     }
     pub fn with_capacity(n: usize) -> PasswordInput {
-        PasswordInput(Input::with_capacity(n))
+        PasswordInput {
+            input: Input::with_capacity(n),
+            revealed: false,
+        }
+    }
+    pub fn toggle_revealed(&mut self) {
+        self.revealed = !self.revealed;
     }
 }
 impl ::std::ops::Deref for PasswordInput {
     type Target = Input;
     fn deref(&self) -> &Input {
-        &self.0
+        &self.input
     }
 }
 impl ::std::ops::DerefMut for PasswordInput {
     fn deref_mut(&mut self) -> &mut Input {
-        &mut self.0
+        &mut self.input
     }
 }
 impl PasswordInput {
     pub fn format_active(&self) -> String {
+        if self.revealed {
+            return self.input.format_active();
+        }
         let len = self.content.graphemes(true).count();
         let stars = "*".repeat(len);
         if self.pos > len {
@@ -863,6 +1247,9 @@ impl PasswordInput {
         }
     }
     pub fn format_inactive(&self) -> String {
+        if self.revealed {
+            return self.input.format_inactive().to_owned();
+        }
         "*".repeat(self.content.graphemes(true).count())
     }
 }
@@ -1000,3 +1387,120 @@ where
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parking_lot::Mutex;
+
+    use crate::tui::screen::Screen;
+
+    // Renders a VSplit, and returns the width and x offset each side was rendered with
+    fn vsplit(width: u16, left: Unit) -> ((u16, u16), (u16, u16)) {
+        let l = Mutex::new((0, 0));
+        let r = Mutex::new((0, 0));
+        VSplit::new(
+            &mut RenderFn::new(|_: &mut _, w, _, x, _| *l.lock() = (w, x)) as &mut dyn Renderable,
+            &mut RenderFn::new(|_: &mut _, w, _, x, _| *r.lock() = (w, x)) as &mut dyn Renderable,
+            None,
+            left,
+            true,
+        )
+        .render(&mut Vec::new(), width, 10, 1, 1);
+        let res = (*l.lock(), *r.lock());
+        res
+    }
+
+    // Renders an HSplit, and returns the height and y offset each side was rendered with
+    fn hsplit(height: u16, top: Unit) -> ((u16, u16), (u16, u16)) {
+        let t = Mutex::new((0, 0));
+        let b = Mutex::new((0, 0));
+        HSplit::new(
+            &mut RenderFn::new(|_: &mut _, _, h, _, y| *t.lock() = (h, y)) as &mut dyn Renderable,
+            &mut RenderFn::new(|_: &mut _, _, h, _, y| *b.lock() = (h, y)) as &mut dyn Renderable,
+            None,
+            top,
+            true,
+        )
+        .render(&mut Vec::new(), 10, height, 1, 1);
+        let res = (*t.lock(), *b.lock());
+        res
+    }
+
+    #[test]
+    fn narrow_vsplit() {
+        // Without room for the divider, both sides get a column
+        assert_eq!(vsplit(2, Unit::Percent(0.2)), ((1, 1), (1, 2)));
+        assert_eq!(vsplit(3, Unit::Percent(0.2)), ((1, 1), (1, 3)));
+        assert_eq!(vsplit(3, Unit::Lines(3)), ((1, 1), (1, 3)));
+        assert_eq!(vsplit(1, Unit::Percent(0.5)), ((0, 1), (1, 1)));
+        assert_eq!(vsplit(0, Unit::Percent(0.5)), ((0, 1), (0, 1)));
+        assert_eq!(vsplit(10, Unit::Percent(0.2)), ((2, 1), (7, 4)));
+    }
+
+    #[test]
+    fn wrapped_text() {
+        // Each line of the wrapped text is drawn on its own row, at the same column
+        let mut frame = Vec::new();
+        Text::<_, align::x::Left, align::y::Top>::new(true, "abcdefghij")
+            .render(&mut frame, 4, 3, 2, 2);
+        let mut screen = Screen::new();
+        screen.update(&frame, 6, 5, &mut Vec::new());
+        assert_eq!(screen.text(), "\n abc-\n def-\n ghij\n\n");
+    }
+
+    #[test]
+    fn dim_render() {
+        let mut target = Vec::new();
+        dim(b"a\x1B[1mb\x1B[mc\x1B[2;3Hd", &mut target);
+        assert_eq!(
+            String::from_utf8(target).unwrap(),
+            "\x1B[2ma\x1B[1m\x1B[2mb\x1B[m\x1B[2mc\x1B[2;3Hd\x1B[22m"
+        );
+    }
+
+    #[test]
+    fn gauge_secondary() {
+        let dim = |s| {
+            format!(
+                "{}{}{}",
+                ColorEscape::gauge_secondary(),
+                s,
+                ColorEscape::reset()
+            )
+        };
+        assert_eq!(
+            Gauge::new(0.25).secondary(0.75).bar(4),
+            format!("█{} ", dim("██"))
+        );
+        // The secondary fill starts after the partial block of the primary one
+        assert_eq!(
+            Gauge::new(0.3).secondary(0.9).bar(4),
+            format!("█▎{}", dim("█▋"))
+        );
+        assert_eq!(Gauge::new(0.5).secondary(0.25).bar(4), "██  ");
+        assert_eq!(Gauge::new(0.5).secondary(f32::NAN).bar(4), "██  ");
+    }
+
+    #[test]
+    fn gauge() {
+        assert_eq!(Gauge::new(0.).bar(4), "    ");
+        assert_eq!(Gauge::new(0.5).bar(4), "██  ");
+        assert_eq!(Gauge::new(0.3).bar(4), "█▎  ");
+        assert_eq!(Gauge::new(1.).bar(4), "████");
+        // Out of range values are clamped, NaN is empty
+        assert_eq!(Gauge::new(-1.).bar(4), "    ");
+        assert_eq!(Gauge::new(3.).bar(4), "████");
+        assert_eq!(Gauge::new(f32::NAN).bar(4), "    ");
+        assert_eq!(Gauge::new(1.).bar(0), "");
+    }
+
+    #[test]
+    fn oversized_split() {
+        // The top is capped, instead of the bottom height underflowing
+        assert_eq!(hsplit(24, Unit::Lines(1000)), ((22, 1), (1, 24)));
+        assert_eq!(hsplit(24, Unit::Percent(2.)), ((22, 1), (1, 24)));
+        assert_eq!(vsplit(10, Unit::Lines(1000)), ((8, 1), (1, 10)));
+    }
+}
@@ -22,6 +22,8 @@ pub mod x {
 
     pub trait Align {
         fn align_offset(lines: &[&str], width: u16) -> Alignment;
+        // Like align_offset for a single line whose width is already known
+        fn align_offset_len(len: u16, width: u16) -> u16;
         fn align(_target: &mut Vec<u8>, _lines: &[&str]) {
             unimplemented!()
         }
@@ -29,7 +31,6 @@ pub mod x {
 
     pub enum Alignment {
         Single(u16),
-        Each(Vec<u16>),
     }
 
     pub struct Left {}
@@ -49,6 +50,9 @@ pub mod x {
         fn align_offset(_: &[&str], _: u16) -> Alignment {
             Alignment::Single(0)
         }
+        fn align_offset_len(_: u16, _: u16) -> u16 {
+            0
+        }
     }
 
     pub struct Right {}
@@ -57,16 +61,22 @@ pub mod x {
             assert!(lines.len() == 1);
             Alignment::Single(width.saturating_sub(count_without_styling(lines[0])))
         }
+        fn align_offset_len(len: u16, width: u16) -> u16 {
+            width.saturating_sub(len)
+        }
     }
 
     pub struct Center {}
     impl Align for Center {
         fn align_offset(lines: &[&str], width: u16) -> Alignment {
-            let mut algns = Vec::with_capacity(lines.len());
-            for l in lines {
-                algns.push((width / 2).saturating_sub((count_without_styling(l) / 2) as u16));
-            }
-            Alignment::Each(algns)
+            assert!(lines.len() == 1);
+            Alignment::Single(Center::align_offset_len(
+                count_without_styling(lines[0]),
+                width,
+            ))
+        }
+        fn align_offset_len(len: u16, width: u16) -> u16 {
+            (width / 2).saturating_sub(len / 2)
         }
     }
 
@@ -81,6 +91,9 @@ pub mod x {
 
             Alignment::Single((width / 2).saturating_sub(max_len / 2))
         }
+        fn align_offset_len(len: u16, width: u16) -> u16 {
+            (width / 2).saturating_sub(len / 2)
+        }
     }
 }
 
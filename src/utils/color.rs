@@ -13,39 +13,222 @@
 // You should have received a copy of the GNU General Public License
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
+use serde::{
+    de::{Deserializer, Error},
+    Deserialize,
+};
 use termion::color;
 
-use std::fmt;
+use std::{borrow::Cow, fmt};
+
+use crate::config::CONFIG;
+
+/// A color as used in the `[theme]` section of the config
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Color {
+    Reset,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    LightBlack,
+    LightRed,
+    LightGreen,
+    LightYellow,
+    LightBlue,
+    LightMagenta,
+    LightCyan,
+    LightWhite,
+    // An index into the 256 color palette
+    Ansi(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    pub fn fg(self) -> ColorEscape {
+        ColorEscape(Cow::Borrowed(match self {
+            Color::Reset => color::Reset.fg_str(),
+            Color::Black => color::Black.fg_str(),
+            Color::Red => color::Red.fg_str(),
+            Color::Green => color::Green.fg_str(),
+            Color::Yellow => color::Yellow.fg_str(),
+            Color::Blue => color::Blue.fg_str(),
+            Color::Magenta => color::Magenta.fg_str(),
+            Color::Cyan => color::Cyan.fg_str(),
+            Color::White => color::White.fg_str(),
+            Color::LightBlack => color::LightBlack.fg_str(),
+            Color::LightRed => color::LightRed.fg_str(),
+            Color::LightGreen => color::LightGreen.fg_str(),
+            Color::LightYellow => color::LightYellow.fg_str(),
+            Color::LightBlue => color::LightBlue.fg_str(),
+            Color::LightMagenta => color::LightMagenta.fg_str(),
+            Color::LightCyan => color::LightCyan.fg_str(),
+            Color::LightWhite => color::LightWhite.fg_str(),
+            Color::Ansi(i) => return ColorEscape::ansi(i),
+            Color::Rgb(r, g, b) => return ColorEscape::rgb(r, g, b),
+        }))
+    }
+
+    pub fn bg(self) -> ColorEscape {
+        ColorEscape(Cow::Borrowed(match self {
+            Color::Reset => color::Reset.bg_str(),
+            Color::Black => color::Black.bg_str(),
+            Color::Red => color::Red.bg_str(),
+            Color::Green => color::Green.bg_str(),
+            Color::Yellow => color::Yellow.bg_str(),
+            Color::Blue => color::Blue.bg_str(),
+            Color::Magenta => color::Magenta.bg_str(),
+            Color::Cyan => color::Cyan.bg_str(),
+            Color::White => color::White.bg_str(),
+            Color::LightBlack => color::LightBlack.bg_str(),
+            Color::LightRed => color::LightRed.bg_str(),
+            Color::LightGreen => color::LightGreen.bg_str(),
+            Color::LightYellow => color::LightYellow.bg_str(),
+            Color::LightBlue => color::LightBlue.bg_str(),
+            Color::LightMagenta => color::LightMagenta.bg_str(),
+            Color::LightCyan => color::LightCyan.bg_str(),
+            Color::LightWhite => color::LightWhite.bg_str(),
+            Color::Ansi(i) => return ColorEscape::ansi_bg(i),
+            Color::Rgb(r, g, b) => return ColorEscape::rgb_bg(r, g, b),
+        }))
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Color, D::Error> {
+        let raw = String::deserialize(de)?;
+        parse_color(&raw).map_err(|e| D::Error::custom(format!("invalid color `{}`: {}", raw, e)))
+    }
+}
+
+/// Parses a color description like `red`, `light_red`, `208` (palette index), or `#ff8700`
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    Ok(match s {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        "light_black" => Color::LightBlack,
+        "light_red" => Color::LightRed,
+        "light_green" => Color::LightGreen,
+        "light_yellow" => Color::LightYellow,
+        "light_blue" => Color::LightBlue,
+        "light_magenta" => Color::LightMagenta,
+        "light_cyan" => Color::LightCyan,
+        "light_white" => Color::LightWhite,
+        s if s.starts_with('#') => {
+            let hex = &s[1..];
+            if hex.len() != 6 || !hex.is_ascii() {
+                return Err("expected 6 hex digits after `#`".to_owned());
+            }
+            let comp = |i: usize| {
+                u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| "invalid hex digit".to_owned())
+            };
+            Color::Rgb(comp(0)?, comp(2)?, comp(4)?)
+        }
+        s => s
+            .parse::<u8>()
+            .map(Color::Ansi)
+            .map_err(|_| "expected a color name, a palette index (0-255), or #rrggbb".to_owned())?,
+    })
+}
+
+/// Maps the roles of colors in the UI to actual colors
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub selection_fg: Color,
+    pub selection_bg: Color,
+    pub error_fg: Color,
+    pub error_bg: Color,
+    pub divider: Color,
+    pub gauge_secondary: Color,
+    pub added_fg: Color,
+    pub zebra_bg: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            selection_fg: Color::Cyan,
+            selection_bg: Color::Ansi(238),
+            error_fg: Color::Red,
+            error_bg: Color::Red,
+            divider: Color::Cyan,
+            gauge_secondary: Color::LightBlack,
+            added_fg: Color::Green,
+            zebra_bg: Color::Ansi(236),
+        }
+    }
+}
 
 /// This codes (erases) termion's Color so as to avoid passing around Box<dyn Color>
-pub struct ColorEscape(&'static str);
+pub struct ColorEscape(Cow<'static, str>);
 
 impl ColorEscape {
     pub fn empty() -> ColorEscape {
-        ColorEscape("")
+        ColorEscape(Cow::Borrowed(""))
     }
-    pub fn inner(&self) -> &'static str {
-        self.0
+    pub fn inner(&self) -> &str {
+        &self.0
     }
 
     pub fn reset() -> ColorEscape {
-        ColorEscape(color::Reset.fg_str())
+        ColorEscape(Cow::Borrowed(color::Reset.fg_str()))
     }
     pub fn black() -> ColorEscape {
-        ColorEscape(color::Black.fg_str())
+        ColorEscape(Cow::Borrowed(color::Black.fg_str()))
     }
-    pub fn red() -> ColorEscape {
-        ColorEscape(color::Red.fg_str())
+    pub fn ansi(i: u8) -> ColorEscape {
+        ColorEscape(Cow::Owned(color::AnsiValue(i).fg_string()))
     }
-    pub fn cyan() -> ColorEscape {
-        ColorEscape(color::Cyan.fg_str())
+    pub fn rgb(r: u8, g: u8, b: u8) -> ColorEscape {
+        ColorEscape(Cow::Owned(color::Rgb(r, g, b).fg_string()))
     }
 
     pub fn reset_bg() -> ColorEscape {
-        ColorEscape(color::Reset.bg_str())
+        ColorEscape(Cow::Borrowed(color::Reset.bg_str()))
+    }
+    pub fn ansi_bg(i: u8) -> ColorEscape {
+        ColorEscape(Cow::Owned(color::AnsiValue(i).bg_string()))
+    }
+    pub fn rgb_bg(r: u8, g: u8, b: u8) -> ColorEscape {
+        ColorEscape(Cow::Owned(color::Rgb(r, g, b).bg_string()))
+    }
+
+    // The configured theme colors
+    pub fn selection() -> ColorEscape {
+        CONFIG.theme.selection_fg.fg()
+    }
+    pub fn selection_bg() -> ColorEscape {
+        CONFIG.theme.selection_bg.bg()
+    }
+    pub fn error() -> ColorEscape {
+        CONFIG.theme.error_fg.fg()
+    }
+    pub fn error_bg() -> ColorEscape {
+        CONFIG.theme.error_bg.bg()
+    }
+    pub fn divider() -> ColorEscape {
+        CONFIG.theme.divider.fg()
+    }
+    pub fn gauge_secondary() -> ColorEscape {
+        CONFIG.theme.gauge_secondary.fg()
+    }
+    pub fn added() -> ColorEscape {
+        CONFIG.theme.added_fg.fg()
     }
-    pub fn red_bg() -> ColorEscape {
-        ColorEscape(color::Red.bg_str())
+    pub fn zebra() -> ColorEscape {
+        CONFIG.theme.zebra_bg.bg()
     }
 }
 
@@ -15,24 +15,44 @@
 // You should have received a copy of the GNU General Public License
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
+use lazy_static::lazy_static;
+use log::warn;
+use parking_lot::Mutex;
+use regex::Regex;
 use synapse_rpc::{
     criterion::{Criterion, Operation, Value},
     message::CMessage,
-    resource::ResourceKind,
+    resource::{ResourceKind, Torrent},
 };
-use termion::{color, event::Key};
+use termion::event::Key;
 
 use crate::{
+    config::CONFIG,
     rpc,
     tui::{widgets, HandleInput, InputResult},
+    utils::{color::ColorEscape, fmt},
 };
 
 use std::sync::Arc;
 
+// The number of input snapshots kept for undo
+const HISTORY_LEN: usize = 50;
+
 #[derive(Clone)]
 enum FilterMode {
     Insensitive,
     Sensitive,
+    // synapse can't match regexes, so names are matched client-side
+    Regex,
+}
+
+lazy_static! {
+    // The mode last chosen, so that it's kept across reconnects
+    static ref MODE: Mutex<FilterMode> = Mutex::new(if CONFIG.filter_case_sensitive {
+        FilterMode::Sensitive
+    } else {
+        FilterMode::Insensitive
+    });
 }
 
 impl FilterMode {
@@ -42,42 +62,87 @@ impl FilterMode {
                 *self = FilterMode::Sensitive;
             }
             FilterMode::Sensitive => {
+                *self = FilterMode::Regex;
+            }
+            FilterMode::Regex => {
                 *self = FilterMode::Insensitive;
             }
         }
     }
+
+    // The operation names are matched with by synapse
+    fn name_op(&self) -> Option<Operation> {
+        match *self {
+            FilterMode::Insensitive => Some(Operation::ILike),
+            FilterMode::Sensitive => Some(Operation::Like),
+            FilterMode::Regex => None,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct Filter {
     mode: FilterMode,
     input: widgets::Input,
+    // Byte ranges of the criteria that could not be parsed
+    invalid: Vec<(usize, usize)>,
+    // synapse has no ratio field, so the ratio criterion is applied client-side
+    ratio: Option<(Operation, f32)>,
+    // The name in regex mode, which is also matched client-side
+    regex: Option<Regex>,
+    // Whether the criterion is negated, and the lowercase label. synapse can't filter by the user
+    // data the labels are kept in, so they are matched client-side
+    labels: Vec<(bool, String)>,
+    // Snapshots of the input for undo/redo, and the index of the current one
+    history: (usize, Vec<String>),
+    // Whether the last edit typed into a word, so that a typed word is undone at once
+    typing: bool,
+    // The tracker host prefix being completed, and the index of the last completion
+    completion: Option<(String, usize)>,
     serial: u64,
     sink: rpc::WsSink,
 }
 
 impl Filter {
-    pub fn new(sink: &rpc::WsSink) -> Filter {
-        let serial = rpc::next_serial();
-        rpc::send(
-            sink,
-            CMessage::FilterSubscribe {
-                serial,
-                kind: ResourceKind::Torrent,
-                criteria: Vec::new(),
-            },
-        );
+    /// Subscribes to the torrents matching the query
+    pub fn new(sink: &rpc::WsSink, query: String) -> Filter {
+        let mode = MODE.lock().clone();
+        // A broken query would be stuck until the user happens to open the filter
+        let query = if parse_criteria(&query, &mode).invalid.is_empty() {
+            query
+        } else {
+            warn!("Not restoring the invalid filter `{}`", query);
+            String::new()
+        };
 
-        Filter {
-            mode: FilterMode::Insensitive,
-            input: widgets::Input::from("".into(), 1),
-            serial,
+        let mut filter = Filter {
+            mode,
+            input: widgets::Input::from(query.clone(), None),
+            invalid: Vec::new(),
+            ratio: None,
+            regex: None,
+            labels: Vec::new(),
+            history: (0, vec![query]),
+            typing: false,
+            completion: None,
+            serial: rpc::next_serial(),
             sink: Arc::clone(sink),
-        }
+        };
+        filter.update();
+        filter
+    }
+
+    pub fn query(&self) -> &str {
+        self.input.inner()
     }
 
     pub fn reset(&mut self) {
         self.input.clear();
+        self.snapshot(false);
+        self.invalid.clear();
+        self.ratio = None;
+        self.regex = None;
+        self.labels.clear();
         rpc::send(
             &self.sink,
             CMessage::FilterSubscribe {
@@ -88,109 +153,135 @@ impl Filter {
         );
     }
 
-    fn update(&self) {
-        let mut criteria = Vec::with_capacity(1);
-        let mut name = String::new();
-
-        for w in self.input.inner().split_whitespace() {
-            let mut l = w.char_indices();
-            // This guards against bigger than 1 byte code points, and a criterion not having been
-            // fully written yet
-            if l.next().map(|l| l.0).unwrap_or(1) != 0
-                || l.next().map(|l| l.0).unwrap_or(0) != 1
-                || l.next().map(|l| l.0).unwrap_or(0) != 2
-            {
-                if !name.is_empty() {
-                    name.push(' ');
-                }
-                name.push_str(w);
-                continue;
-            }
+    pub fn unsubscribe(&self) {
+        rpc::send(
+            &self.sink,
+            CMessage::FilterUnsubscribe {
+                serial: rpc::next_serial(),
+                filter_serial: self.serial,
+            },
+        );
+    }
 
-            match &w[..1] {
-                "t" => {
-                    if &w[1..2] == ":" {
-                        criteria.push(Criterion {
-                            field: "tracker_urls".into(),
-                            op: Operation::Has,
-                            value: Value::S(w[2..].to_owned()),
-                        });
-                    }
-                }
-                "p" => {
-                    if let Ok(n) = w[2..].parse::<f32>() {
-                        criteria.push(Criterion {
-                            field: "progress".into(),
-                            op: match &w[1..2] {
-                                ":" => Operation::Eq,
-                                "<" => Operation::LT,
-                                ">" => Operation::GT,
-                                _ => {
-                                    // TODO: Insert red BG
-                                    continue;
-                                }
-                            },
-                            value: Value::F(n / 100.),
-                        })
-                    } else {
-                        // TODO: Insert red BG
-                    }
-                }
-                "s" => {
-                    if let Ok(n) = w[2..].parse::<f32>() {
-                        criteria.push(Criterion {
-                            field: "size".into(),
-                            op: match &w[1..2] {
-                                "<" => Operation::LTE,
-                                ">" => Operation::GTE,
-                                _ => {
-                                    // TODO: Insert red BG
-                                    continue;
-                                }
-                            },
-                            value: Value::F(n * 1024. * 1024.),
-                        });
-                    } else {
-                        criteria.push(Criterion {
-                            field: "status".into(),
-                            op: Operation::Eq,
-                            value: match &w[2..3] {
-                                "i" => Value::S("idle".to_owned()),
-                                "s" => Value::S("seeding".to_owned()),
-                                "l" => Value::S("leeching".to_owned()),
-                                "e" => Value::S("error".to_owned()),
-                                "p" => Value::S("paused".to_owned()),
-                                "n" => Value::S("pending".to_owned()),
-                                "h" => Value::S("hashing".to_owned()),
-                                "m" => Value::S("magnet".to_owned()),
-                                _ => {
-                                    // TODO: Insert red BG
-                                    continue;
-                                }
-                            },
-                        });
-                    }
-                }
-                _ => {
-                    if !name.is_empty() {
-                        name.push(' ');
-                    }
-                    name.push_str(w);
-                }
+    fn snapshot(&mut self, typing: bool) {
+        let (ref mut pos, ref mut history) = self.history;
+        if history[*pos] == self.input.inner() {
+            return;
+        }
+
+        history.truncate(*pos + 1);
+        if typing && self.typing && *pos > 0 {
+            history[*pos] = self.input.inner().to_owned();
+        } else {
+            history.push(self.input.inner().to_owned());
+            if history.len() > HISTORY_LEN {
+                history.remove(0);
             }
+            *pos = history.len() - 1;
         }
+        self.typing = typing;
+    }
 
-        if !name.is_empty() {
-            criteria.push(Criterion {
-                field: "name".into(),
-                op: match self.mode {
-                    FilterMode::Insensitive => Operation::ILike,
-                    FilterMode::Sensitive => Operation::Like,
-                },
-                value: Value::S(name),
-            });
+    fn restore(&mut self, pos: usize) {
+        self.history.0 = pos;
+        self.input = widgets::Input::from(self.history.1[pos].clone(), None);
+        self.typing = false;
+        self.update();
+    }
+
+    /// Completes the tracker host of the `t:` criterion before the cursor, repeated calls cycle
+    /// through the matching hosts
+    pub fn complete(&mut self, hosts: &[&str]) -> InputResult {
+        let word = self.input.word_before_cursor();
+        let (neg, word) = match word.strip_prefix('!') {
+            Some(word) => ("!", word),
+            None => ("", &word[..]),
+        };
+        if !word.starts_with("t:") {
+            return InputResult::Key(Key::Char('\t'));
         }
 
+        let (prefix, idx) = match self.completion.take() {
+            Some((prefix, idx)) => (prefix, idx + 1),
+            None => (word[2..].to_owned(), 0),
+        };
+        let mut candidates = hosts
+            .iter()
+            .filter(|h| h.starts_with(&*prefix))
+            .collect::<Vec<_>>();
+        candidates.sort();
+        candidates.dedup();
+        if candidates.is_empty() {
+            return InputResult::Key(Key::Char('\t'));
+        }
+
+        let idx = idx % candidates.len();
+        self.input.delete_word();
+        for c in format!("{}t:{}", neg, candidates[idx]).chars() {
+            self.input.push(c);
+        }
+        // A unique match is final
+        if candidates.len() > 1 {
+            self.completion = Some((prefix, idx));
+        }
+        self.snapshot(false);
+        self.update();
+        InputResult::Rerender
+    }
+
+    /// Whether the torrent matches the criteria that synapse can not filter by
+    pub fn matches(&self, t: &Torrent) -> bool {
+        if let Some(ref regex) = self.regex {
+            if !regex.is_match(fmt::torrent_name(t)) {
+                return false;
+            }
+        }
+        let label = fmt::torrent_label(t).map(|l| l.to_lowercase());
+        if self
+            .labels
+            .iter()
+            .any(|&(neg, ref l)| (label.as_ref() == Some(l)) == neg)
+        {
+            return false;
+        }
+        self.ratio
+            .as_ref()
+            .map(|&(ref op, n)| {
+                let rat = if t.transferred_down == 0 {
+                    0.
+                } else {
+                    t.transferred_up as f32 / t.transferred_down as f32
+                };
+                match *op {
+                    Operation::LT => rat < n,
+                    Operation::LTE => rat <= n,
+                    Operation::GT => rat > n,
+                    Operation::GTE => rat >= n,
+                    Operation::Neq => (rat - n).abs() >= 0.005,
+                    _ => (rat - n).abs() < 0.005,
+                }
+            })
+            .unwrap_or(true)
+    }
+
+    /// Whether some criteria are only applied by `matches`
+    pub fn is_client_side(&self) -> bool {
+        self.ratio.is_some() || self.regex.is_some() || !self.labels.is_empty()
+    }
+
+    fn update(&mut self) {
+        let Parsed {
+            criteria,
+            invalid,
+            ratio,
+            regex,
+            labels,
+        } = parse_criteria(self.input.inner(), &self.mode);
+        self.invalid = invalid;
+        self.ratio = ratio;
+        self.regex = regex;
+        self.labels = labels;
+
         rpc::send(
             &self.sink,
             CMessage::FilterSubscribe {
@@ -202,21 +293,28 @@ impl Filter {
     }
 
     pub fn format(&self, active: bool) -> String {
-        let (c_s, c_e, cnt) = if active {
+        let (c_s, c_e) = if active {
             (
-                format!("{}", color::Fg(color::Cyan)),
-                format!("{}", color::Fg(color::Reset)),
-                self.input.format_active(),
+                format!("{}", ColorEscape::selection()),
+                format!("{}", ColorEscape::reset()),
             )
         } else {
-            ("".into(), "".into(), self.input.format_inactive().into())
+            ("".into(), "".into())
         };
+        // Malformed criteria are highlighted, so it's clear that they are not applied
+        let cnt = self.input.format_highlighted(
+            active,
+            &self.invalid,
+            ColorEscape::error_bg().inner(),
+            ColorEscape::reset_bg().inner(),
+        );
         format!(
             "{}{}{}{}",
             c_s,
             match self.mode {
                 FilterMode::Insensitive => "Filter[i]: ",
                 FilterMode::Sensitive => "Filter[s]: ",
+                FilterMode::Regex => "Filter[r]: ",
             },
             c_e,
             cnt
@@ -224,19 +322,251 @@ impl Filter {
     }
 }
 
+// The result of parsing the filter input
+struct Parsed {
+    criteria: Vec<Criterion>,
+    // Byte ranges of the criteria that could not be parsed
+    invalid: Vec<(usize, usize)>,
+    ratio: Option<(Operation, f32)>,
+    regex: Option<Regex>,
+    labels: Vec<(bool, String)>,
+}
+
+fn parse_criteria(content: &str, mode: &FilterMode) -> Parsed {
+    let mut criteria = Vec::with_capacity(1);
+    let mut name = String::new();
+    let mut invalid = Vec::new();
+    let mut ratio = None;
+    let mut labels = Vec::new();
+    // The byte ranges of the words that make up the name
+    let mut name_spans = Vec::new();
+
+    let mut off = 0;
+    for w in content.split_whitespace() {
+        let start = off + content[off..].find(w).unwrap_or(0);
+        off = start + w.len();
+        let span = (start, off);
+
+        // A leading ! negates the criterion
+        let (neg, w) = if w.len() > 1 && w.starts_with('!') {
+            (true, &w[1..])
+        } else {
+            (false, w)
+        };
+
+        if w.len() > 1 && w.starts_with('@') {
+            labels.push((neg, w[1..].to_lowercase()));
+            continue;
+        }
+
+        let mut l = w.char_indices();
+        // This guards against bigger than 1 byte code points, and a criterion not having been
+        // fully written yet. Words like "ubuntu" are names, only an operator makes a specifier
+        if l.next().map(|l| l.0).unwrap_or(1) != 0
+            || l.next().map(|l| l.0).unwrap_or(0) != 1
+            || l.next().map(|l| l.0).unwrap_or(0) != 2
+            || !["t", "p", "r", "u", "d", "s"].contains(&&w[..1])
+            || ![":", "<", ">"].contains(&&w[1..2])
+        {
+            if let (true, Some(op)) = (neg, mode.name_op()) {
+                criteria.push(Criterion {
+                    field: "name".into(),
+                    op: invert(op),
+                    value: Value::S(w.to_owned()),
+                });
+                continue;
+            }
+            if !name.is_empty() {
+                name.push(' ');
+            }
+            // A regex may well start with a !
+            name.push_str(&content[span.0..span.1]);
+            name_spans.push(span);
+            continue;
+        }
+
+        // Alternatives separated by | can only be expressed for the status, via In
+        if w[2..].contains('|') && &w[..2] != "s:" {
+            invalid.push(span);
+            continue;
+        }
+
+        let op = match (&w[..1], &w[1..2]) {
+            ("t", ":") => Operation::Has,
+            ("p", ":") | ("r", ":") | ("s", ":") => Operation::Eq,
+            ("p", "<") | ("r", "<") | ("u", "<") | ("d", "<") => Operation::LT,
+            ("p", ">") | ("r", ">") | ("u", ">") | ("d", ">") => Operation::GT,
+            ("s", "<") => Operation::LTE,
+            ("s", ">") => Operation::GTE,
+            _ => {
+                invalid.push(span);
+                continue;
+            }
+        };
+        let op = if neg { invert(op) } else { op };
+
+        let criterion = match &w[..1] {
+            "t" => Criterion {
+                field: "tracker_urls".into(),
+                op,
+                value: Value::S(w[2..].to_owned()),
+            },
+            "s" if op == Operation::Eq || op == Operation::Neq => {
+                let mut statuses = Vec::new();
+                for s in w[2..].split('|') {
+                    statuses.push(Value::S(
+                        match s {
+                            "i" => "idle",
+                            "s" => "seeding",
+                            "l" => "leeching",
+                            "e" => "error",
+                            "p" => "paused",
+                            "n" | "pe" => "pending",
+                            "h" => "hashing",
+                            "m" => "magnet",
+                            _ => {
+                                invalid.push(span);
+                                break;
+                            }
+                        }
+                        .to_owned(),
+                    ));
+                }
+                if statuses.len() != w[2..].split('|').count() {
+                    continue;
+                }
+
+                if statuses.len() == 1 {
+                    Criterion {
+                        field: "status".into(),
+                        op,
+                        value: statuses.pop().unwrap(),
+                    }
+                } else {
+                    Criterion {
+                        field: "status".into(),
+                        op: if op == Operation::Eq {
+                            Operation::In
+                        } else {
+                            Operation::NotIn
+                        },
+                        value: Value::V(statuses),
+                    }
+                }
+            }
+            f => {
+                let n = match w[2..].parse::<f32>() {
+                    Ok(n) => n,
+                    Err(_) => {
+                        invalid.push(span);
+                        continue;
+                    }
+                };
+                match f {
+                    "r" => {
+                        // Only one ratio criterion can be applied client-side
+                        if ratio.is_some() {
+                            invalid.push(span);
+                        } else {
+                            ratio = Some((op, n));
+                        }
+                        continue;
+                    }
+                    "p" => Criterion {
+                        field: "progress".into(),
+                        op,
+                        value: Value::F(n / 100.),
+                    },
+                    "u" => Criterion {
+                        field: "rate_up".into(),
+                        op,
+                        value: Value::F(n * 1024.),
+                    },
+                    "d" => Criterion {
+                        field: "rate_down".into(),
+                        op,
+                        value: Value::F(n * 1024.),
+                    },
+                    _ => Criterion {
+                        field: "size".into(),
+                        op,
+                        value: Value::F(n * 1024. * 1024.),
+                    },
+                }
+            }
+        };
+
+        // Don't send a subscription that can never match anything
+        if criteria.iter().any(|c: &Criterion| {
+            c.field == criterion.field && c.value == criterion.value && c.op == invert(criterion.op)
+        }) {
+            invalid.push(span);
+        } else {
+            criteria.push(criterion);
+        }
+    }
+
+    let mut regex = None;
+    if !name.is_empty() {
+        match mode.name_op() {
+            Some(op) => criteria.push(Criterion {
+                field: "name".into(),
+                op,
+                value: Value::S(name),
+            }),
+            None => match Regex::new(&name) {
+                Ok(r) => regex = Some(r),
+                Err(_) => invalid.extend(name_spans),
+            },
+        }
+    }
+
+    Parsed {
+        criteria,
+        invalid,
+        ratio,
+        regex,
+        labels,
+    }
+}
+
+// Every operation the filter uses has an inverse, so any criterion can be negated
+fn invert(op: Operation) -> Operation {
+    match op {
+        Operation::Eq => Operation::Neq,
+        Operation::Neq => Operation::Eq,
+        Operation::GT => Operation::LTE,
+        Operation::GTE => Operation::LT,
+        Operation::LT => Operation::GTE,
+        Operation::LTE => Operation::GT,
+        Operation::Like => Operation::NotLike,
+        Operation::NotLike => Operation::Like,
+        Operation::ILike => Operation::NotILike,
+        Operation::NotILike => Operation::ILike,
+        Operation::Has => Operation::NotHas,
+        Operation::NotHas => Operation::Has,
+        Operation::In => Operation::NotIn,
+        Operation::NotIn => Operation::In,
+    }
+}
+
 impl HandleInput for Filter {
     fn input(&mut self, k: Key, _: u16, _: u16) -> InputResult {
+        self.completion = None;
         match k {
             Key::Ctrl('s') => {
                 self.mode.cycle();
+                *MODE.lock() = self.mode.clone();
                 self.update();
             }
             Key::Backspace => {
                 self.input.backspace();
+                self.snapshot(false);
                 self.update();
             }
             Key::Delete => {
                 self.input.delete();
+                self.snapshot(false);
                 self.update();
             }
             Key::Home => {
@@ -247,8 +577,34 @@ impl HandleInput for Filter {
             }
             Key::Left => self.input.cursor_left(),
             Key::Right => self.input.cursor_right(),
+            Key::Alt('b') => self.input.word_left(),
+            Key::Alt('f') => self.input.word_right(),
+            Key::Ctrl('w') => {
+                self.input.delete_word();
+                self.snapshot(false);
+                self.update();
+            }
+            Key::Ctrl('k') => {
+                self.input.kill_to_end();
+                self.snapshot(false);
+                self.update();
+            }
+            Key::Ctrl('u') => {
+                self.input.kill_to_start();
+                self.snapshot(false);
+                self.update();
+            }
+            Key::Ctrl('z') if self.history.0 > 0 => {
+                let pos = self.history.0 - 1;
+                self.restore(pos);
+            }
+            Key::Ctrl('y') if self.history.0 + 1 < self.history.1.len() => {
+                let pos = self.history.0 + 1;
+                self.restore(pos);
+            }
             Key::Char(c) => {
                 self.input.push(c);
+                self.snapshot(!c.is_whitespace());
                 self.update();
             }
             _ => {
@@ -257,4 +613,291 @@ impl HandleInput for Filter {
         }
         InputResult::Rerender
     }
+
+    fn paste(&mut self, text: &str, _: u16, _: u16) -> InputResult {
+        self.completion = None;
+        self.input.push_str(text);
+        self.snapshot(false);
+        self.update();
+        InputResult::Rerender
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Records the messages instead of sending them
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<CMessage>>);
+
+    impl rpc::MessageSink for Recorder {
+        fn send(&self, msg: CMessage) {
+            self.0.lock().push(msg);
+        }
+    }
+
+    fn parse(s: &str) -> Parsed {
+        parse_criteria(s, &FilterMode::Insensitive)
+    }
+
+    fn criteria(p: &Parsed) -> Vec<(&str, Operation, &Value)> {
+        p.criteria
+            .iter()
+            .map(|c| (&*c.field, c.op, &c.value))
+            .collect()
+    }
+
+    #[test]
+    fn progress() {
+        let p = parse("p:50");
+        assert_eq!(
+            criteria(&p),
+            vec![("progress", Operation::Eq, &Value::F(0.5))]
+        );
+        assert!(p.invalid.is_empty());
+
+        let p = parse("p<10");
+        assert_eq!(
+            criteria(&p),
+            vec![("progress", Operation::LT, &Value::F(0.1))]
+        );
+    }
+
+    #[test]
+    fn size_is_inclusive() {
+        let p = parse("s>100 s<2.5");
+        assert_eq!(
+            criteria(&p),
+            vec![
+                ("size", Operation::GTE, &Value::F(100. * 1024. * 1024.)),
+                ("size", Operation::LTE, &Value::F(2.5 * 1024. * 1024.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn rates() {
+        let p = parse("u>1 d<20");
+        assert_eq!(
+            criteria(&p),
+            vec![
+                ("rate_up", Operation::GT, &Value::F(1024.)),
+                ("rate_down", Operation::LT, &Value::F(20. * 1024.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn tracker() {
+        let p = parse("t:example !t:other");
+        assert_eq!(
+            criteria(&p),
+            vec![
+                ("tracker_urls", Operation::Has, &Value::S("example".into())),
+                ("tracker_urls", Operation::NotHas, &Value::S("other".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn status() {
+        let p = parse("s:s");
+        assert_eq!(
+            criteria(&p),
+            vec![("status", Operation::Eq, &Value::S("seeding".into()))]
+        );
+
+        let p = parse("!s:i");
+        assert_eq!(
+            criteria(&p),
+            vec![("status", Operation::Neq, &Value::S("idle".into()))]
+        );
+
+        let p = parse("s:s|l|pe");
+        let statuses = Value::V(vec![
+            Value::S("seeding".into()),
+            Value::S("leeching".into()),
+            Value::S("pending".into()),
+        ]);
+        assert_eq!(criteria(&p), vec![("status", Operation::In, &statuses)]);
+
+        let p = parse("!s:s|l|pe");
+        assert_eq!(criteria(&p), vec![("status", Operation::NotIn, &statuses)]);
+    }
+
+    #[test]
+    fn ratio() {
+        let p = parse("r>1.5");
+        assert!(p.criteria.is_empty());
+        assert_eq!(p.ratio, Some((Operation::GT, 1.5)));
+
+        // Only the first ratio is applied
+        let p = parse("r>1.5 !r:2");
+        assert_eq!(p.ratio, Some((Operation::GT, 1.5)));
+        assert_eq!(p.invalid, vec![(6, 10)]);
+    }
+
+    #[test]
+    fn name() {
+        let p = parse("foo p>5 bar !baz");
+        assert_eq!(
+            criteria(&p),
+            vec![
+                ("progress", Operation::GT, &Value::F(0.05)),
+                ("name", Operation::NotILike, &Value::S("baz".into())),
+                ("name", Operation::ILike, &Value::S("foo bar".into())),
+            ]
+        );
+
+        let p = parse_criteria("Foo", &FilterMode::Sensitive);
+        assert_eq!(
+            criteria(&p),
+            vec![("name", Operation::Like, &Value::S("Foo".into()))]
+        );
+    }
+
+    #[test]
+    fn incomplete_and_unknown_words_are_names() {
+        // Too short to be a criterion, an unknown prefix, and multi-byte code points
+        let p = parse("p: x:1 ü:1 pü1");
+        assert_eq!(
+            criteria(&p),
+            vec![("name", Operation::ILike, &Value::S("p: x:1 ü:1 pü1".into()))]
+        );
+        assert!(p.invalid.is_empty());
+    }
+
+    #[test]
+    fn words_starting_like_specifiers_are_names() {
+        let p = parse("ubuntu debian p=5");
+        assert_eq!(
+            criteria(&p),
+            vec![(
+                "name",
+                Operation::ILike,
+                &Value::S("ubuntu debian p=5".into())
+            )]
+        );
+        assert!(p.invalid.is_empty());
+
+        let p = parse_criteria("rust.* s:s", &FilterMode::Regex);
+        assert_eq!(p.regex.unwrap().as_str(), "rust.*");
+        assert!(p.invalid.is_empty());
+    }
+
+    #[test]
+    fn invalid() {
+        let p = parse("p:abc t:a|b  s:x t<5 s:s|q u>1");
+        assert_eq!(
+            criteria(&p),
+            vec![("rate_up", Operation::GT, &Value::F(1024.))]
+        );
+        assert_eq!(
+            p.invalid,
+            vec![(0, 5), (6, 11), (13, 16), (17, 20), (21, 26)]
+        );
+    }
+
+    #[test]
+    fn label() {
+        let p = parse("@Work foo !@old @");
+        assert_eq!(
+            criteria(&p),
+            vec![("name", Operation::ILike, &Value::S("foo @".into()))]
+        );
+        assert_eq!(
+            p.labels,
+            vec![(false, "work".to_owned()), (true, "old".to_owned())]
+        );
+
+        let sink: rpc::WsSink = Arc::new(Recorder::default());
+        let filter = Filter::new(&sink, "@work !@old".to_owned());
+        let mut t = Torrent {
+            user_data: serde_json::json!({ "label": "Work" }),
+            ..Default::default()
+        };
+        assert!(filter.is_client_side());
+        assert!(filter.matches(&t));
+        t.user_data = serde_json::json!({});
+        assert!(!filter.matches(&t));
+    }
+
+    #[test]
+    fn reset_matches_everything() {
+        let sink: rpc::WsSink = Arc::new(Recorder::default());
+        let mut filter = Filter::new(&sink, String::new());
+        filter.mode = FilterMode::Regex;
+        filter.input = widgets::Input::from("^zzz @work".to_owned(), None);
+        filter.update();
+        let t = Torrent {
+            name: Some("debian".to_owned()),
+            user_data: serde_json::json!({}),
+            ..Default::default()
+        };
+        assert!(filter.is_client_side());
+        assert!(!filter.matches(&t));
+
+        filter.reset();
+        assert!(!filter.is_client_side());
+        assert!(filter.matches(&t));
+    }
+
+    #[test]
+    fn contradiction() {
+        let p = parse("s:s !s:s");
+        assert_eq!(
+            criteria(&p),
+            vec![("status", Operation::Eq, &Value::S("seeding".into()))]
+        );
+        assert_eq!(p.invalid, vec![(4, 8)]);
+
+        let p = parse("p>5 !p>5");
+        assert_eq!(p.invalid, vec![(4, 8)]);
+    }
+
+    #[test]
+    fn repeated_words_have_their_own_span() {
+        let p = parse("p:x p:x");
+        assert_eq!(p.invalid, vec![(0, 3), (4, 7)]);
+    }
+
+    #[test]
+    fn initial_query() {
+        let recorder = Arc::new(Recorder::default());
+        let sink: rpc::WsSink = recorder.clone();
+
+        let filter = Filter::new(&sink, "s:s foo".to_owned());
+        assert_eq!(filter.query(), "s:s foo");
+        let msg = recorder.0.lock().pop();
+        match msg {
+            Some(CMessage::FilterSubscribe { criteria, .. }) => assert_eq!(criteria.len(), 2),
+            _ => panic!("expected a filter subscription"),
+        }
+
+        // Restoring a broken query would subscribe to nothing
+        let filter = Filter::new(&sink, "p:abc".to_owned());
+        assert_eq!(filter.query(), "");
+        let msg = recorder.0.lock().pop();
+        match msg {
+            Some(CMessage::FilterSubscribe { criteria, .. }) => assert!(criteria.is_empty()),
+            _ => panic!("expected a filter subscription"),
+        }
+    }
+
+    #[test]
+    fn regex() {
+        let p = parse_criteria("s:s ^foo.*bar !x$", &FilterMode::Regex);
+        assert_eq!(
+            criteria(&p),
+            vec![("status", Operation::Eq, &Value::S("seeding".into()))]
+        );
+        let regex = p.regex.unwrap();
+        assert_eq!(regex.as_str(), "^foo.*bar !x$");
+        assert!(regex.is_match("foo and bar !x"));
+
+        let p = parse_criteria("p>5 foo( bar", &FilterMode::Regex);
+        assert!(p.regex.is_none());
+        assert_eq!(p.invalid, vec![(4, 8), (9, 12)]);
+    }
 }
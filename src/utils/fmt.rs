@@ -15,10 +15,43 @@
 // You should have received a copy of the GNU General Public License
 // along with Axon.  If not, see <http://www.gnu.org/licenses/>.
 
-use chrono::{DateTime, Local, Utc};
+use chrono::{DateTime, Duration, Local, Utc};
+use synapse_rpc::resource::Torrent;
+
+use std::cmp;
+
+use crate::config::{SizeUnits, CONFIG};
+
+/// The time relative dates are formatted against
+#[cfg(not(test))]
+pub fn now() -> DateTime<Utc> {
+    Utc::now()
+}
+
+// Fixed in tests, so that relative dates render the same on every run
+#[cfg(test)]
+pub fn now() -> DateTime<Utc> {
+    use chrono::TimeZone;
+    Utc.timestamp_opt(1_560_000_000, 0).unwrap()
+}
 
 pub fn date_diff_now(date: DateTime<Utc>) -> String {
-    let dur = Local::now().signed_duration_since(date);
+    date_diff(date, now())
+}
+
+/// Like `date_diff_now`, but only the two most significant units
+pub fn date_diff_now_compact(date: DateTime<Utc>) -> String {
+    fmt_duration_compact(now().signed_duration_since(date))
+}
+
+// Formats the time between the dates as e.g. `1w 2d 03:04:05`
+fn date_diff(from: DateTime<Utc>, to: DateTime<Utc>) -> String {
+    fmt_duration(to.signed_duration_since(from))
+}
+
+/// Formats the duration as e.g. `1w 2d 03:04:05`, negative durations (i.e. clock skew) as zero
+pub fn fmt_duration(dur: Duration) -> String {
+    let dur = cmp::max(dur, Duration::zero());
     let w = dur.num_weeks();
     let d = dur.num_days() - dur.num_weeks() * 7;
     let h = dur.num_hours() - dur.num_days() * 24;
@@ -32,11 +65,7 @@ pub fn date_diff_now(date: DateTime<Utc>) -> String {
         } + if d == 0 { 0 } else { 2 },
     );
     if w > 0 {
-        if d > 0 {
-            res += &*format!("{}w", w);
-        } else {
-            res += &*format!("{}w ", w);
-        }
+        res += &*format!("{}w ", w);
     }
     if d > 0 {
         res += &*format!("{}d ", d);
@@ -44,47 +73,200 @@ pub fn date_diff_now(date: DateTime<Utc>) -> String {
     res + &*format!("{:02}:{:02}:{:02}", h, m, s)
 }
 
+/// Formats the duration with its two most significant units, e.g. `3w 2d` or `5h 12m`,
+/// negative durations (i.e. clock skew) as zero
+pub fn fmt_duration_compact(dur: Duration) -> String {
+    let secs = cmp::max(dur.num_seconds(), 0);
+    let units = [
+        ('w', 7 * 86400),
+        ('d', 86400),
+        ('h', 3600),
+        ('m', 60),
+        ('s', 1),
+    ];
+    let first = match units.iter().position(|&(_, len)| secs >= len) {
+        Some(first) => first,
+        None => return "0s".to_owned(),
+    };
+    let (c, len) = units[first];
+    let mut res = format!("{}{}", secs / len, c);
+    if let Some(&(c_next, len_next)) = units.get(first + 1) {
+        let next = secs % len / len_next;
+        if next != 0 {
+            res += &*format!(" {}{}", next, c_next);
+        }
+    }
+    res
+}
+
+/// Like `date_diff_now` with an "ago" suffix, but "never" for the epoch, which synapse uses for
+/// dates that have not happened yet
+pub fn date_ago(date: DateTime<Utc>) -> String {
+    if date.timestamp() <= 0 {
+        "never".to_owned()
+    } else {
+        format!("{} ago", date_diff_now(date))
+    }
+}
+
+/// Formats the date in the local timezone
+pub fn fmt_datetime_local(date: DateTime<Utc>) -> String {
+    date.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// Formats the time it takes to transfer the remaining bytes at the rate in bytes per second
+pub fn fmt_eta(remaining: u64, rate: u64) -> String {
+    if remaining == 0 {
+        return "done".to_owned();
+    } else if rate == 0 {
+        return "∞".to_owned();
+    }
+
+    let secs = remaining.div_ceil(rate);
+    let (d, h, m, s) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if d > 0 {
+        format!("{}d{}h", d, h)
+    } else if h > 0 {
+        format!("{}h{}m", h, m)
+    } else if m > 0 {
+        format!("{}m{}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+/// The name a torrent is displayed with, which is the one set by renaming it in axon if any, or
+/// its path if the name is not known yet
+pub fn torrent_name(t: &Torrent) -> &str {
+    t.user_data
+        .get("name")
+        .and_then(|n| n.as_str())
+        .filter(|n| !n.is_empty())
+        .or(t.name.as_deref())
+        .unwrap_or(&t.path)
+}
+
+/// The label that groups the torrent, kept in the user data like by other synapse clients
+pub fn torrent_label(t: &Torrent) -> Option<&str> {
+    t.user_data
+        .get("label")
+        .and_then(|l| l.as_str())
+        .filter(|l| !l.is_empty())
+}
+
+pub fn torrent_eta(t: &Torrent) -> String {
+    if t.progress >= 1. {
+        fmt_eta(0, t.rate_down)
+    } else if let Some(size) = t.size {
+        // Avoid claiming to be done because of rounding
+        let remaining = cmp::max(((1. - t.progress) * size as f32) as u64, 1);
+        fmt_eta(remaining, t.rate_down)
+    } else {
+        fmt_eta(1, 0)
+    }
+}
+
+/// Formats the ratio of uploaded to downloaded bytes, which is "∞" if only uploaded, and "-" if
+/// nothing was transferred yet
+pub fn fmt_ratio(up: u64, down: u64) -> String {
+    match (up, down) {
+        (0, 0) => "-".to_owned(),
+        (_, 0) => "∞".to_owned(),
+        (up, down) => format!("{:.2}", up as f32 / down as f32),
+    }
+}
+
 pub trait FormatSize {
     fn fmt_size(self) -> String;
     fn fmt_size_align(self) -> String;
+    fn fmt_rate(self) -> String;
+    fn fmt_rate_align(self) -> String;
+}
+
+static BINARY: [&str; 9] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB", "ZiB", "YiB"];
+static DECIMAL: [&str; 9] = ["B", "kB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
+static BITS: [&str; 9] = [
+    "bps", "kbps", "Mbps", "Gbps", "Tbps", "Pbps", "Ebps", "Zbps", "Ybps",
+];
+
+// The divisor, units, and width of the widest unit
+fn size_scale() -> (f32, &'static [&'static str; 9], usize) {
+    match CONFIG.size_units {
+        SizeUnits::Binary => (1024., &BINARY, 3),
+        SizeUnits::Decimal => (1000., &DECIMAL, 2),
+    }
+}
+
+// Bit rates are always shown with decimal prefixes
+fn rate_scale() -> (f32, &'static [&'static str; 9], usize) {
+    if CONFIG.rate_bits {
+        (1000., &BITS, 4)
+    } else {
+        size_scale()
+    }
+}
+
+fn rate_factor() -> f32 {
+    if CONFIG.rate_bits {
+        8.
+    } else {
+        1.
+    }
+}
+
+/// The width of a size formatted by `fmt_size_align`
+pub fn size_align_width() -> usize {
+    7 + size_scale().2
+}
+
+/// The width of a rate formatted by `fmt_rate_align`
+pub fn rate_align_width() -> usize {
+    7 + rate_scale().2
+}
+
+fn scale(mut size: f32, div: f32) -> (f32, usize) {
+    let mut idx = 0;
+    while size >= div {
+        size /= div;
+        idx += 1;
+    }
+
+    // Cheat to avoid overly long alignments
+    if size >= 1000. {
+        size /= div;
+        idx += 1;
+    }
+
+    (size, idx)
 }
 
-static SCALE: [&'static str; 9] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB", "ZiB", "YiB"];
 macro_rules! impl_fmt_size {
     ($impl_ty:ty) => {
         impl FormatSize for $impl_ty {
             fn fmt_size(self) -> String {
-                let mut size = self as f32;
-                let mut idx = 0;
-                while size >= 1024. {
-                    size /= 1024.;
-                    idx += 1;
-                }
-
-                // Cheat to avoid overly long alignments
-                if size >= 1000. {
-                    size = 1024. / size;
-                    idx += 1;
-                }
-
-                format!("{:.2} {}", size, SCALE[idx])
+                let (div, units, _) = size_scale();
+                let (size, idx) = scale(self as f32, div);
+                format!("{:.2} {}", size, units[idx])
             }
 
             fn fmt_size_align(self) -> String {
-                let mut size = self as f32;
-                let mut idx = 0;
-                while size >= 1024. {
-                    size /= 1024.;
-                    idx += 1;
-                }
-
-                // Cheat to avoid overly long alignments
-                if size >= 1000. {
-                    size = 1024. / size;
-                    idx += 1;
-                }
-
-                format!("{: >6.2} {}", size, SCALE[idx])
+                let (div, units, w) = size_scale();
+                let (size, idx) = scale(self as f32, div);
+                format!("{: >6.2} {: <w$}", size, units[idx], w = w)
+            }
+
+            fn fmt_rate(self) -> String {
+                let (div, units, _) = rate_scale();
+                let (size, idx) = scale(self as f32 * rate_factor(), div);
+                format!("{:.2} {}", size, units[idx])
+            }
+
+            fn fmt_rate_align(self) -> String {
+                let (div, units, w) = rate_scale();
+                let (size, idx) = scale(self as f32 * rate_factor(), div);
+                format!("{: >6.2} {: <w$}", size, units[idx], w = w)
             }
         }
     };
@@ -94,3 +276,53 @@ impl_fmt_size!(usize);
 impl_fmt_size!(u32);
 impl_fmt_size!(u64);
 impl_fmt_size!(i64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    #[test]
+    fn date_diff_format() {
+        let from = Utc.with_ymd_and_hms(2019, 1, 1, 12, 0, 0).unwrap();
+        assert_eq!(date_diff(from, from), "00:00:00");
+        assert_eq!(
+            date_diff(from, Utc.with_ymd_and_hms(2019, 1, 1, 15, 4, 5).unwrap()),
+            "03:04:05"
+        );
+        assert_eq!(
+            date_diff(from, Utc.with_ymd_and_hms(2019, 1, 3, 12, 0, 1).unwrap()),
+            "2d 00:00:01"
+        );
+        assert_eq!(
+            date_diff(from, Utc.with_ymd_and_hms(2019, 1, 17, 13, 0, 0).unwrap()),
+            "2w 2d 01:00:00"
+        );
+        assert_eq!(
+            date_diff(from, Utc.with_ymd_and_hms(2019, 1, 15, 12, 0, 0).unwrap()),
+            "2w 00:00:00"
+        );
+        assert_eq!(
+            date_diff(from, Utc.with_ymd_and_hms(2019, 1, 1, 11, 0, 0).unwrap()),
+            "00:00:00"
+        );
+    }
+
+    #[test]
+    fn duration_compact() {
+        let secs = Duration::seconds;
+        assert_eq!(fmt_duration_compact(secs(0)), "0s");
+        assert_eq!(fmt_duration_compact(secs(-30)), "0s");
+        assert_eq!(fmt_duration_compact(secs(42)), "42s");
+        assert_eq!(fmt_duration_compact(secs(5 * 3600 + 12 * 60 + 7)), "5h 12m");
+        assert_eq!(fmt_duration_compact(secs(3600 + 30)), "1h");
+        assert_eq!(fmt_duration_compact(secs(23 * 86400 + 3600)), "3w 2d");
+    }
+
+    #[test]
+    fn date_diff_now_is_utc() {
+        let past = now() - chrono::Duration::seconds(3 * 3600 + 5);
+        assert_eq!(date_diff_now(past), "03:00:05");
+    }
+}
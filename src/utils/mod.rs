@@ -19,17 +19,173 @@ pub mod align;
 pub mod color;
 pub mod filter;
 pub mod fmt;
+pub mod template;
 
+use log::warn;
 use unicode_segmentation::UnicodeSegmentation;
-use unicode_width::UnicodeWidthStr;
+use unicode_width::UnicodeWidthChar;
+
+use std::{
+    borrow::Cow,
+    io,
+    process::{Command, Stdio},
+    thread,
+};
+
+use crate::config::CONFIG;
+
+#[cfg(target_os = "macos")]
+const DEFAULT_OPENER: &str = "open";
+#[cfg(not(target_os = "macos"))]
+const DEFAULT_OPENER: &str = "xdg-open";
+
+// The commands that print the clipboard, the first one that works is used
+#[cfg(target_os = "macos")]
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[&["pbpaste"]];
+#[cfg(not(target_os = "macos"))]
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-out", "-selection", "clipboard"],
+    &["xsel", "--output", "--clipboard"],
+];
+
+/// The number of columns a terminal uses to display the grapheme cluster
+pub fn grapheme_width(g: &str) -> usize {
+    let mut cs = g.chars();
+    // The first char determines the width, the rest are combining marks, joined emoji, and
+    // modifiers that are drawn on top of it
+    let base = match cs.next() {
+        Some(c) => c,
+        None => return 0,
+    };
+    match (base, base.width().unwrap_or(0)) {
+        (_, 0) => 0,
+        // A pair of regional indicators is a flag
+        ('\u{1F1E6}'..='\u{1F1FF}', _) => 2,
+        // The emoji presentation selector widens text style symbols like ❤
+        (_, 1) if cs.any(|c| c == '\u{FE0F}') => 2,
+        (_, w) => w,
+    }
+}
 
 pub fn count(l: &str) -> usize {
-    l.graphemes(true).map(|g| g.width()).sum()
+    l.graphemes(true).map(grapheme_width).sum()
+}
+
+/// Shortens l to at most width columns by replacing its middle with "…", the end gets the larger
+/// half. Must not contain escape codes
+pub fn truncate_middle<'a>(l: &'a str, width: usize) -> Cow<'a, str> {
+    if count(l) <= width {
+        return Cow::Borrowed(l);
+    } else if width == 0 {
+        return Cow::Borrowed("");
+    }
+
+    let gs: Vec<_> = l.graphemes(true).map(|g| (g, grapheme_width(g))).collect();
+    let avail = width - 1;
+    let mut tail_w = 0;
+    let tail = gs
+        .iter()
+        .rev()
+        .take_while(|&&(_, w)| {
+            tail_w += w;
+            tail_w <= avail - avail / 2
+        })
+        .count();
+    let tail_w: usize = gs[gs.len() - tail..].iter().map(|g| g.1).sum();
+    let mut head_w = 0;
+    let head = gs
+        .iter()
+        .take_while(|&&(_, w)| {
+            head_w += w;
+            head_w <= avail - tail_w
+        })
+        .count();
+
+    let mut res: String = gs[..head].iter().map(|g| g.0).collect();
+    res.push('…');
+    res.extend(gs[gs.len() - tail..].iter().map(|g| g.0));
+    Cow::Owned(res)
+}
+
+/// Opens the path with `open_command`, or the platform's opener, without waiting for it
+pub fn open_path(path: &str) -> Result<(), String> {
+    if path.trim().is_empty() {
+        return Err("The path is empty".to_owned());
+    }
+
+    let mut cmd = match CONFIG.open_command {
+        // Runs in a shell like `pass_command`, the path is its first argument so it needs no quoting
+        Some(ref open) => {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c")
+                .arg(format!("{} \"$1\"", open))
+                .arg("sh")
+                .arg(path);
+            cmd
+        }
+        None => {
+            let mut cmd = Command::new(DEFAULT_OPENER);
+            cmd.arg(path);
+            cmd
+        }
+    };
+    // The opener must not draw over the TUI, or steal its input
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    // Reap the opener once it exits, by then its failure can only be logged
+    thread::spawn(move || match child.wait() {
+        Ok(status) if !status.success() => warn!("The opener failed: {}", status),
+        Err(e) => warn!("Failed to wait for the opener: {}", e),
+        Ok(_) => {}
+    });
+    Ok(())
+}
+
+/// The text in the system clipboard, read with the platform's clipboard tool
+pub fn paste_from_clipboard() -> Result<String, String> {
+    let mut err = None;
+    for cmd in CLIPBOARD_COMMANDS {
+        // E.g. wl-paste fails outside of Wayland, while xclip may still work
+        let out = match Command::new(cmd[0])
+            .args(&cmd[1..])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+        {
+            Ok(ref out) if !out.status.success() => {
+                err = Some(format!("{} failed: {}", cmd[0], out.status));
+                continue;
+            }
+            Ok(out) => out,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                err = Some(format!("Failed to run {}: {}", cmd[0], e));
+                continue;
+            }
+        };
+        return String::from_utf8(out.stdout)
+            .map_err(|_| "The clipboard does not contain text".to_owned());
+    }
+    Err(err.unwrap_or_else(|| {
+        format!(
+            "Reading the clipboard needs one of {}",
+            CLIPBOARD_COMMANDS
+                .iter()
+                .map(|cmd| cmd[0])
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }))
 }
 
 pub fn count_without_styling(l: &str) -> u16 {
     let mut count = 0;
-    let mut gs = l.graphemes(true).map(|g| (g, g.width()));
+    let mut gs = l.graphemes(true).map(|g| (g, grapheme_width(g)));
 
     while let Some((g, w)) = gs.next() {
         if g == "\x1B" {
@@ -42,3 +198,42 @@ pub fn count_without_styling(l: &str) -> u16 {
 
     count as u16
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grapheme_widths() {
+        assert_eq!(grapheme_width("a"), 1);
+        assert_eq!(grapheme_width(""), 0);
+        assert_eq!(grapheme_width("日"), 2);
+        // Combining marks are drawn on top of their base
+        assert_eq!(grapheme_width("e\u{301}"), 1);
+        assert_eq!(grapheme_width("\u{301}"), 0);
+        // A family of joined emoji is drawn as one
+        assert_eq!(grapheme_width("👨\u{200D}👩\u{200D}👧"), 2);
+        assert_eq!(grapheme_width("🇩🇪"), 2);
+        assert_eq!(grapheme_width("❤\u{FE0F}"), 2);
+
+        assert_eq!(count("ascii"), 5);
+        assert_eq!(count("日本語"), 6);
+        assert_eq!(count("cafe\u{301} 👨\u{200D}👩\u{200D}👧"), 7);
+    }
+
+    #[test]
+    fn truncate_middle_keeps_end() {
+        assert_eq!(truncate_middle("debian-10.iso", 20), "debian-10.iso");
+        assert_eq!(truncate_middle("debian-10.0.0-amd64.iso", 10), "debi…4.iso");
+        assert_eq!(truncate_middle("debian-10.iso", 1), "…");
+        assert_eq!(truncate_middle("debian-10.iso", 0), "");
+        // Wide graphemes are not split, the space they'd leave is not filled
+        assert_eq!(truncate_middle("日本語の名前.mkv", 8), "日….mkv");
+    }
+
+    #[test]
+    fn open_empty_path() {
+        assert!(open_path("").is_err());
+        assert!(open_path("  ").is_err());
+    }
+}
@@ -0,0 +1,262 @@
+// This file is part of Axon.
+//
+// Axon is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Axon is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Axon.  If not, see <http://www.gnu.org/licenses/>.
+
+use synapse_rpc::resource::Torrent;
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::{borrow::Cow, mem};
+
+use crate::{
+    config::{Truncation, CONFIG},
+    utils::{
+        self,
+        fmt::{self, FormatSize},
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Name,
+    Label,
+    Progress,
+    Eta,
+    Status,
+    RateUp,
+    RateDown,
+    ThrottleUp,
+    ThrottleDown,
+    Ratio,
+    Up,
+    Down,
+    Size,
+    Peers,
+    Priority,
+    Path,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        Some(match name {
+            "name" => Field::Name,
+            "label" => Field::Label,
+            "progress" => Field::Progress,
+            "eta" => Field::Eta,
+            "status" => Field::Status,
+            "rate_up" => Field::RateUp,
+            "rate_down" => Field::RateDown,
+            "throttle_up" => Field::ThrottleUp,
+            "throttle_down" => Field::ThrottleDown,
+            "ratio" => Field::Ratio,
+            "up" => Field::Up,
+            "down" => Field::Down,
+            "size" => Field::Size,
+            "peers" => Field::Peers,
+            "priority" => Field::Priority,
+            "path" => Field::Path,
+            _ => return None,
+        })
+    }
+
+    fn format<'a>(self, t: &'a Torrent) -> Cow<'a, str> {
+        let throttle = |th: Option<i64>| match th {
+            // The global throttle applies
+            None => "*".into(),
+            Some(-1) => "∞".into(),
+            Some(th) => th.fmt_rate().into(),
+        };
+        match self {
+            Field::Name => fmt::torrent_name(t).into(),
+            Field::Label => fmt::torrent_label(t).unwrap_or("").into(),
+            Field::Progress => format!("{}%", (t.progress * 100.).round()).into(),
+            Field::Eta => fmt::torrent_eta(t).into(),
+            Field::Status => t.status.as_str().into(),
+            Field::RateUp => t.rate_up.fmt_rate().into(),
+            Field::RateDown => t.rate_down.fmt_rate().into(),
+            Field::ThrottleUp => throttle(t.throttle_up),
+            Field::ThrottleDown => throttle(t.throttle_down),
+            Field::Ratio => fmt::fmt_ratio(t.transferred_up, t.transferred_down).into(),
+            Field::Up => t.transferred_up.fmt_size().into(),
+            Field::Down => t.transferred_down.fmt_size().into(),
+            Field::Size => t.size.map_or_else(|| "?".into(), |s| s.fmt_size().into()),
+            Field::Peers => t.peers.to_string().into(),
+            Field::Priority => t.priority.to_string().into(),
+            Field::Path => (&*t.path).into(),
+        }
+    }
+
+    // Text is aligned left in its width, numbers right
+    fn left_aligned(self) -> bool {
+        matches!(
+            self,
+            Field::Name | Field::Label | Field::Status | Field::Path
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Text(String),
+    // The field, and the columns it is padded or cut to
+    Field(Field, Option<usize>),
+}
+
+/// A row of the torrent list like `{name:40} {progress:4} {rate_down}`, parsed once since it is
+/// applied to every row. `{{` and `}}` are literal braces
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template(Vec<Part>);
+
+impl Template {
+    pub fn parse(s: &str) -> Result<Template, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut cs = s.chars();
+        while let Some(c) = cs.next() {
+            match c {
+                '{' if cs.as_str().starts_with('{') => {
+                    cs.next();
+                    text.push('{');
+                }
+                '}' if cs.as_str().starts_with('}') => {
+                    cs.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = cs.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| format!("unclosed placeholder `{{{}`", rest))?;
+                    let placeholder = &rest[..end];
+                    let (name, width) = match placeholder.find(':') {
+                        Some(i) => {
+                            let width = placeholder[i + 1..].parse::<usize>().map_err(|_| {
+                                format!("invalid width of placeholder `{{{}}}`", placeholder)
+                            })?;
+                            (&placeholder[..i], Some(width))
+                        }
+                        None => (placeholder, None),
+                    };
+                    let field = Field::from_name(name)
+                        .ok_or_else(|| format!("unknown placeholder `{{{}}}`", name))?;
+
+                    if !text.is_empty() {
+                        parts.push(Part::Text(mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(field, width));
+                    cs = rest[end + 1..].chars();
+                }
+                '}' => return Err("unmatched `}`, write `}}` for a literal one".to_owned()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template(parts))
+    }
+
+    /// The row of the torrent, without styling
+    pub fn render(&self, t: &Torrent) -> String {
+        let mut row = String::new();
+        for part in &self.0 {
+            match *part {
+                Part::Text(ref text) => row.push_str(text),
+                Part::Field(field, None) => row.push_str(&field.format(t)),
+                Part::Field(field, Some(width)) => {
+                    let val = field.format(t);
+                    let val = match (field, CONFIG.name_truncation) {
+                        (Field::Name, Truncation::Middle) => utils::truncate_middle(&val, width),
+                        _ => truncate_end(&val, width),
+                    };
+                    let pad = " ".repeat(width.saturating_sub(utils::count(&val)));
+                    if field.left_aligned() {
+                        row.push_str(&val);
+                        row.push_str(&pad);
+                    } else {
+                        row.push_str(&pad);
+                        row.push_str(&val);
+                    }
+                }
+            }
+        }
+        row
+    }
+}
+
+// Shortens l to at most width columns by replacing its end with "…"
+fn truncate_end<'a>(l: &'a str, width: usize) -> Cow<'a, str> {
+    if utils::count(l) <= width {
+        return Cow::Borrowed(l);
+    } else if width == 0 {
+        return Cow::Borrowed("");
+    }
+
+    let mut w = 0;
+    let mut cut: String = l
+        .graphemes(true)
+        .take_while(|g| {
+            w += utils::grapheme_width(g);
+            w < width
+        })
+        .collect();
+    cut.push('…');
+    Cow::Owned(cut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use synapse_rpc::resource::Status;
+
+    fn torrent() -> Torrent {
+        Torrent {
+            name: Some("debian-10.iso".to_owned()),
+            status: Status::Leeching,
+            progress: 0.42,
+            rate_down: 2048,
+            throttle_up: Some(-1),
+            size: Some(1024),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fields() {
+        let row = Template::parse("{name} {progress} {status} {rate_down} [{throttle_up}]")
+            .unwrap()
+            .render(&torrent());
+        assert_eq!(row, "debian-10.iso 42% leeching 2.00 KiB [∞]");
+    }
+
+    #[test]
+    fn widths() {
+        let template = Template::parse("{name:8}|{progress:5}|{status:10}|").unwrap();
+        assert_eq!(template.render(&torrent()), "debian-…|  42%|leeching  |");
+    }
+
+    #[test]
+    fn braces() {
+        let template = Template::parse("{{{size}}}").unwrap();
+        assert_eq!(template.render(&torrent()), "{1.00 KiB}");
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Template::parse("{name").is_err());
+        assert!(Template::parse("{nmae}").is_err());
+        assert!(Template::parse("{name:x}").is_err());
+        assert!(Template::parse("name}").is_err());
+    }
+}